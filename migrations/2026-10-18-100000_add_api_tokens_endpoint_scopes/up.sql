@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE api_tokens ADD COLUMN endpoint_scopes TEXT[];
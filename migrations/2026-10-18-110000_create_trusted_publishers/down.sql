@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE api_tokens DROP COLUMN plugin_id;
+ALTER TABLE api_tokens DROP COLUMN expires_at;
+
+DROP TABLE trusted_publishers;
@@ -0,0 +1,15 @@
+-- Your SQL goes here
+create table trusted_publishers (
+    id                  SERIAL PRIMARY KEY,
+    plugin_id           INTEGER NOT NULL,
+    repository          VARCHAR NOT NULL,
+    workflow            VARCHAR NOT NULL,
+    environment         VARCHAR,
+    created_at          timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT "trusted_publishers_plugin_id_fkey" FOREIGN KEY ("plugin_id") REFERENCES "public"."plugins"("id")
+);
+
+CREATE INDEX trusted_publishers_repository ON trusted_publishers (repository);
+
+ALTER TABLE api_tokens ADD COLUMN expires_at timestamp;
+ALTER TABLE api_tokens ADD COLUMN plugin_id INTEGER REFERENCES plugins (id);
@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE plugins ADD COLUMN repository_verified BOOLEAN NOT NULL DEFAULT false;
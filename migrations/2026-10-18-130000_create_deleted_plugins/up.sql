@@ -0,0 +1,14 @@
+-- Your SQL goes here
+create table deleted_plugins (
+    id                  SERIAL PRIMARY KEY,
+    user_id             INTEGER NOT NULL,
+    author              VARCHAR NOT NULL,
+    name                VARCHAR NOT NULL,
+    deleted_by          INTEGER NOT NULL,
+    deleted_at          timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    reserved_until      timestamp NOT NULL,
+    CONSTRAINT "deleted_plugins_user_id_fkey" FOREIGN KEY ("user_id") REFERENCES "public"."users"("id"),
+    CONSTRAINT "deleted_plugins_deleted_by_fkey" FOREIGN KEY ("deleted_by") REFERENCES "public"."users"("id")
+);
+
+CREATE INDEX deleted_plugins_author_name ON deleted_plugins (author, name);
@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE versions ADD COLUMN yank_reason VARCHAR;
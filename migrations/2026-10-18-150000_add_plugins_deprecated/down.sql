@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE plugins DROP COLUMN deprecation_message;
+ALTER TABLE plugins DROP COLUMN deprecated;
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE plugins ADD COLUMN deprecated BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE plugins ADD COLUMN deprecation_message VARCHAR;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE users DROP COLUMN name;
+ALTER TABLE users DROP COLUMN gh_avatar;
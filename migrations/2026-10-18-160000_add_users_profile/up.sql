@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE users ADD COLUMN gh_avatar VARCHAR;
+ALTER TABLE users ADD COLUMN name VARCHAR;
@@ -0,0 +1,10 @@
+-- Your SQL goes here
+create table version_downloads (
+    version_id          INTEGER NOT NULL,
+    date                date NOT NULL DEFAULT CURRENT_DATE,
+    downloads           INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (version_id, date),
+    CONSTRAINT "version_downloads_version_id_fkey" FOREIGN KEY ("version_id") REFERENCES "public"."versions"("id")
+);
+
+CREATE INDEX version_downloads_date ON version_downloads (date);
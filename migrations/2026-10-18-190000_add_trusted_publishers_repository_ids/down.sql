@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX trusted_publishers_repository_id;
+
+ALTER TABLE trusted_publishers DROP COLUMN repository_owner_id;
+ALTER TABLE trusted_publishers DROP COLUMN repository_id;
@@ -0,0 +1,5 @@
+-- Your SQL goes here
+ALTER TABLE trusted_publishers ADD COLUMN repository_id VARCHAR;
+ALTER TABLE trusted_publishers ADD COLUMN repository_owner_id VARCHAR;
+
+CREATE INDEX trusted_publishers_repository_id ON trusted_publishers (repository_id);
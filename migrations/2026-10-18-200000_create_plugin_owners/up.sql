@@ -0,0 +1,11 @@
+-- Your SQL goes here
+create table plugin_owners (
+    plugin_id           INTEGER NOT NULL,
+    user_id             INTEGER NOT NULL,
+    created_at          timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (plugin_id, user_id),
+    CONSTRAINT "plugin_owners_plugin_id_fkey" FOREIGN KEY ("plugin_id") REFERENCES "public"."plugins"("id"),
+    CONSTRAINT "plugin_owners_user_id_fkey" FOREIGN KEY ("user_id") REFERENCES "public"."users"("id")
+);
+
+CREATE INDEX plugin_owners_user_id ON plugin_owners (user_id);
@@ -0,0 +1,57 @@
+//! HTTP caching of plugin metadata and files.
+
+use axum::{
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+
+/// For everything pinned to a version, which can't be published again
+pub(crate) const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+/// For `latest` lookups, which change whenever a version is published or
+/// yanked
+pub(crate) const SHORT_LIVED: &str = "public, max-age=60";
+
+/// A strong ETag hashing what identifies the content.
+pub(crate) fn etag(parts: &[&[u8]]) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part);
+    }
+    let hash = format!("{:x}", hasher.finalize());
+    format!("\"{}\"", &hash[..32])
+}
+
+/// Whether the client's `If-None-Match` has `etag`.
+pub(crate) fn not_modified(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+/// Answers with `304 Not Modified` when the client has the content already,
+/// and otherwise with `resp`, tagged with `etag` and `cache_control`.
+pub(crate) fn cached(
+    headers: &HeaderMap,
+    etag: &str,
+    cache_control: &'static str,
+    resp: impl IntoResponse,
+) -> Response {
+    let mut resp = if not_modified(headers, etag) {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        resp.into_response()
+    };
+    let resp_headers = resp.headers_mut();
+    resp_headers.insert(header::ETAG, HeaderValue::from_str(etag).unwrap());
+    resp_headers.insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static(cache_control),
+    );
+    resp
+}
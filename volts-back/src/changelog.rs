@@ -0,0 +1,181 @@
+//! Release notes of a version, taken from its section of the `CHANGELOG.md`
+//! in the archive unless the publisher gives them.
+
+/// Longest release notes a version can have, longer sections of a changelog
+/// are cut off
+pub(crate) const MAX_RELEASE_NOTES_LEN: usize = 10_000;
+
+/// The level and title of a Markdown heading like `## [1.2.0] - 2026-10-18`.
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let title = line.trim_start_matches('#');
+    let level = line.len() - title.len();
+    if level == 0 || level > 6 || !(title.is_empty() || title.starts_with(' ')) {
+        return None;
+    }
+    Some((level, title.trim()))
+}
+
+/// Whether the heading is for `version`, written as `1.2.0`, `v1.2.0`,
+/// `[1.2.0]` or `[1.2.0](link)` at the start of it, possibly followed by a
+/// date or a name. Headings mentioning the version later on, like
+/// `Changes since 1.2.0`, aren't.
+fn is_version_heading(title: &str, version: &str) -> bool {
+    let title = title.strip_prefix('[').unwrap_or(title);
+    let title = title.strip_prefix('v').unwrap_or(title);
+    title
+        .split(|c: char| c.is_whitespace() || matches!(c, ']' | '(' | ')'))
+        .next()
+        == Some(version)
+}
+
+/// The section of the changelog under the heading of `version`, up to the
+/// next heading of the same or a higher level. Lines in code blocks, where
+/// comments look like headings, aren't taken as headings.
+pub(crate) fn release_notes(changelog: &str, version: &str) -> Option<String> {
+    let mut section: Option<(usize, Vec<&str>)> = None;
+    let mut in_code_block = false;
+    for line in changelog.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+        }
+        let heading = if in_code_block { None } else { heading(line) };
+        match section.as_mut() {
+            Some((level, lines)) => {
+                if matches!(heading, Some((l, _)) if l <= *level) {
+                    break;
+                }
+                lines.push(line);
+            }
+            None => {
+                section = heading
+                    .filter(|(_, title)| is_version_heading(title, version))
+                    .map(|(level, _)| (level, Vec::new()));
+            }
+        }
+    }
+
+    let notes = section?.1.join("\n");
+    let notes = notes.trim();
+    if notes.is_empty() {
+        return None;
+    }
+    Some(notes.chars().take(MAX_RELEASE_NOTES_LEN).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHANGELOG: &str = "\
+# Changelog
+
+## Unreleased
+
+- Nothing yet
+
+## Changes since 1.1.0
+
+Summary of everything after 1.1.0.
+
+## [1.2.0] - 2026-10-18
+
+### Added
+
+- Dark theme
+
+```sh
+# Not a heading
+## 1.1.0
+```
+
+### Fixed
+
+- Icons
+
+## v1.1.0
+
+- First release
+
+# Older
+
+## 1.0.0
+
+- Preview
+";
+
+    #[test]
+    fn finds_version_heading_styles() {
+        assert!(is_version_heading("1.2.0", "1.2.0"));
+        assert!(is_version_heading("v1.2.0", "1.2.0"));
+        assert!(is_version_heading("[1.2.0] - 2026-10-18", "1.2.0"));
+        assert!(is_version_heading("[v1.2.0]", "1.2.0"));
+        assert!(is_version_heading(
+            "[1.2.0](https://github.com/alice/theme/compare/v1.1.0...v1.2.0) (2026-10-18)",
+            "1.2.0"
+        ));
+        assert!(is_version_heading("1.2.0 (2026-10-18)", "1.2.0"));
+    }
+
+    #[test]
+    fn only_matches_version_at_start() {
+        assert!(!is_version_heading("Changes since 1.2.0", "1.2.0"));
+        assert!(!is_version_heading("Release v1.2.0", "1.2.0"));
+        assert!(!is_version_heading("1.2.0-beta.1", "1.2.0"));
+        assert!(!is_version_heading("11.2.0", "1.2.0"));
+        assert!(!is_version_heading("", "1.2.0"));
+    }
+
+    #[test]
+    fn takes_section_of_version() {
+        let notes = release_notes(CHANGELOG, "1.2.0").unwrap();
+        assert!(notes.starts_with("### Added\n\n- Dark theme"));
+        assert!(notes.ends_with("### Fixed\n\n- Icons"));
+        assert!(!notes.contains("Summary of everything"));
+        assert!(!notes.contains("First release"));
+    }
+
+    #[test]
+    fn skips_headings_mentioning_version_later() {
+        let notes = release_notes(CHANGELOG, "1.1.0").unwrap();
+        assert_eq!(notes, "- First release");
+    }
+
+    #[test]
+    fn ignores_headings_in_code_blocks() {
+        let notes = release_notes(CHANGELOG, "1.2.0").unwrap();
+        assert!(notes.contains("# Not a heading\n## 1.1.0\n```"));
+
+        let changelog = "```md\n## 2.0.0\n- In an example\n```\n\n## 2.0.0\n\n- Real notes\n";
+        assert_eq!(
+            release_notes(changelog, "2.0.0").as_deref(),
+            Some("- Real notes")
+        );
+    }
+
+    #[test]
+    fn stops_at_higher_level_heading() {
+        let changelog = "### 1.0.0\n\n- Fix\n\n## Previous releases\n\n- Other\n";
+        assert_eq!(release_notes(changelog, "1.0.0").as_deref(), Some("- Fix"));
+        assert_eq!(
+            release_notes(CHANGELOG, "1.0.0").as_deref(),
+            Some("- Preview")
+        );
+    }
+
+    #[test]
+    fn missing_or_empty_sections_have_no_notes() {
+        assert_eq!(release_notes(CHANGELOG, "3.0.0"), None);
+        assert_eq!(
+            release_notes("## 1.0.0\n\n## 0.9.0\n- Old\n", "1.0.0"),
+            None
+        );
+        assert_eq!(release_notes("", "1.0.0"), None);
+    }
+
+    #[test]
+    fn caps_notes_length() {
+        let changelog = format!("## 1.0.0\n\n{}\n", "é".repeat(MAX_RELEASE_NOTES_LEN + 100));
+        let notes = release_notes(&changelog, "1.0.0").unwrap();
+        assert_eq!(notes.chars().count(), MAX_RELEASE_NOTES_LEN);
+    }
+}
@@ -1,16 +1,26 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, collections::BTreeMap};
 
 use anyhow::Result;
+use chrono::{NaiveDate, NaiveDateTime};
 use diesel::BelongingToDsl;
+use diesel::BoolExpressionMethods;
 use diesel::ExpressionMethods;
+use diesel::GroupedBy;
 use diesel::NullableExpressionMethods;
+use diesel::OptionalExtension;
 use diesel::QueryDsl;
 use diesel_async::RunQueryDsl;
 use diesel_async::{pooled_connection::deadpool::Pool, AsyncPgConnection};
+use futures::FutureExt;
 use volts_core::db::models::Plugin;
-use volts_core::db::models::{ApiToken, User, Version};
-use volts_core::db::schema::{api_tokens, plugins, users, versions};
-use volts_core::EncodeApiToken;
+use volts_core::db::models::{ApiToken, DeletedPlugin, TrustedPublisher, User, Version};
+use volts_core::db::schema::{
+    api_tokens, deleted_plugins, plugin_owners, plugins, trusted_publishers, users,
+    version_downloads, versions,
+};
+use volts_core::{EncodeApiToken, TOKEN_SCOPE_PUBLISH};
+
+use crate::github::GithubRepository;
 
 #[derive(Clone)]
 pub struct DbPool {
@@ -48,14 +58,24 @@ pub struct NewUser<'a> {
     pub gh_id: i32,
     pub gh_login: &'a str,
     pub gh_access_token: Cow<'a, str>,
+    pub gh_avatar: Option<&'a str>,
+    pub name: Option<&'a str>,
 }
 
 impl<'a> NewUser<'a> {
-    pub fn new(gh_id: i32, gh_login: &'a str, gh_access_token: &'a str) -> Self {
+    pub fn new(
+        gh_id: i32,
+        gh_login: &'a str,
+        gh_access_token: &'a str,
+        gh_avatar: Option<&'a str>,
+        name: Option<&'a str>,
+    ) -> Self {
         NewUser {
             gh_id,
             gh_login,
             gh_access_token: Cow::Borrowed(gh_access_token),
+            gh_avatar,
+            name,
         }
     }
 
@@ -71,6 +91,8 @@ impl<'a> NewUser<'a> {
             .set((
                 gh_login.eq(excluded(gh_login)),
                 gh_access_token.eq(excluded(gh_access_token)),
+                gh_avatar.eq(excluded(gh_avatar)),
+                name.eq(excluded(name)),
             ))
             .get_result(conn)
             .await?;
@@ -94,6 +116,8 @@ pub async fn find_user_by_gh_login(conn: &mut AsyncPgConnection, gh_login: &str)
 pub async fn list_tokens(conn: &mut AsyncPgConnection, user: &User) -> Result<Vec<ApiToken>> {
     let tokens: Vec<ApiToken> = ApiToken::belonging_to(&user)
         .filter(api_tokens::revoked.eq(false))
+        // Leave out the short-lived tokens of trusted publishers
+        .filter(api_tokens::expires_at.is_null())
         .order(api_tokens::created_at.desc())
         .load(conn)
         .await?;
@@ -104,6 +128,7 @@ pub async fn insert_token(
     conn: &mut AsyncPgConnection,
     user: &User,
     name: &str,
+    scopes: &[String],
 ) -> Result<EncodeApiToken> {
     let token = crate::util::SecureToken::new_token();
 
@@ -112,6 +137,7 @@ pub async fn insert_token(
             api_tokens::user_id.eq(user.id),
             api_tokens::name.eq(name),
             api_tokens::token.eq(token.token()),
+            api_tokens::endpoint_scopes.eq(scopes),
         ))
         .get_result(conn)
         .await?;
@@ -122,12 +148,50 @@ pub async fn insert_token(
     })
 }
 
-pub async fn revoke_token(conn: &mut AsyncPgConnection, user: &User, id: i32) -> Result<()> {
-    diesel::update(ApiToken::belonging_to(&user).find(id))
+/// Deletes the short-lived tokens of trusted publishers once they expired.
+pub async fn delete_expired_tokens(conn: &mut AsyncPgConnection) -> Result<usize> {
+    let deleted = diesel::delete(
+        api_tokens::table.filter(api_tokens::expires_at.lt(chrono::Utc::now().naive_utc())),
+    )
+    .execute(conn)
+    .await?;
+    Ok(deleted)
+}
+
+/// Creates a token that can only publish `plugin` until `expires_at`.
+pub async fn insert_trusted_token(
+    conn: &mut AsyncPgConnection,
+    plugin: &Plugin,
+    name: &str,
+    expires_at: NaiveDateTime,
+) -> Result<EncodeApiToken> {
+    let token = crate::util::SecureToken::new_token();
+
+    let model: ApiToken = diesel::insert_into(api_tokens::table)
+        .values((
+            api_tokens::user_id.eq(plugin.user_id),
+            api_tokens::name.eq(name),
+            api_tokens::token.eq(token.token()),
+            api_tokens::endpoint_scopes.eq(vec![TOKEN_SCOPE_PUBLISH.to_string()]),
+            api_tokens::expires_at.eq(expires_at),
+            api_tokens::plugin_id.eq(plugin.id),
+        ))
+        .get_result(conn)
+        .await?;
+
+    Ok(EncodeApiToken {
+        token: model,
+        plaintext: token.plaintext().into(),
+    })
+}
+
+/// Returns false if the user has no token with that id.
+pub async fn revoke_token(conn: &mut AsyncPgConnection, user: &User, id: i32) -> Result<bool> {
+    let revoked = diesel::update(ApiToken::belonging_to(&user).find(id))
         .set(api_tokens::revoked.eq(true))
         .execute(conn)
         .await?;
-    Ok(())
+    Ok(revoked > 0)
 }
 
 pub async fn find_api_token(conn: &mut AsyncPgConnection, api_token: &str) -> Result<ApiToken> {
@@ -138,7 +202,8 @@ pub async fn find_api_token(conn: &mut AsyncPgConnection, api_token: &str) -> Re
 
     let tokens = api_tokens
         .filter(revoked.eq(false))
-        .filter(token.eq(token_.token()));
+        .filter(token.eq(token_.token()))
+        .filter(expires_at.is_null().or(expires_at.gt(now.nullable())));
 
     let token_ = update(tokens)
         .set(last_used_at.eq(now.nullable()))
@@ -158,6 +223,7 @@ pub struct NewPlugin<'a> {
     pub repository: Option<&'a str>,
     pub downloads: i32,
     pub wasm: bool,
+    pub repository_verified: bool,
 }
 
 impl<'a> NewPlugin<'a> {
@@ -167,6 +233,7 @@ impl<'a> NewPlugin<'a> {
         display_name: &'a str,
         description: &'a str,
         repository: Option<&'a str>,
+        repository_verified: bool,
         wasm: bool,
     ) -> Self {
         NewPlugin {
@@ -176,6 +243,7 @@ impl<'a> NewPlugin<'a> {
             description,
             downloads: 0,
             repository,
+            repository_verified,
             wasm,
         }
     }
@@ -192,6 +260,7 @@ impl<'a> NewPlugin<'a> {
                 display_name.eq(excluded(display_name)),
                 description.eq(excluded(description)),
                 repository.eq(excluded(repository)),
+                repository_verified.eq(excluded(repository_verified)),
                 wasm.eq(excluded(wasm)),
                 updated_at.eq(chrono::Utc::now().naive_utc()),
             ))
@@ -207,25 +276,31 @@ pub struct NewVersion<'a> {
     pub plugin_id: i32,
     pub num: &'a str,
     pub yanked: bool,
+    pub checksum: Option<&'a str>,
+    pub release_notes: Option<&'a str>,
 }
 
 impl<'a> NewVersion<'a> {
-    pub fn new(plugin_id: i32, num: &'a str) -> Self {
+    pub fn new(
+        plugin_id: i32,
+        num: &'a str,
+        checksum: &'a str,
+        release_notes: Option<&'a str>,
+    ) -> Self {
         NewVersion {
             plugin_id,
             num,
             yanked: false,
+            checksum: Some(checksum),
+            release_notes,
         }
     }
 
-    pub async fn create_or_update(&self, conn: &mut AsyncPgConnection) -> Result<Version> {
-        use volts_core::db::schema::versions::dsl::*;
-
-        let version: Version = diesel::insert_into(versions)
+    /// Fails with a unique violation if the version was published already,
+    /// published versions can't be replaced.
+    pub async fn create(&self, conn: &mut AsyncPgConnection) -> Result<Version> {
+        let version: Version = diesel::insert_into(versions::table)
             .values(self)
-            .on_conflict((plugin_id, num))
-            .do_update()
-            .set(updated_at.eq(chrono::Utc::now().naive_utc()))
             .get_result(conn)
             .await?;
         Ok(version)
@@ -257,13 +332,412 @@ pub async fn modify_plugin_version_yank(
     plugin: &Plugin,
     num: &str,
     is_yanked: bool,
+    reason: Option<&str>,
 ) -> Result<Version> {
+    let reason = if is_yanked { reason } else { None };
     let version = diesel::update(Version::belonging_to(plugin).filter(versions::num.eq(num)))
         .set((
             versions::yanked.eq(is_yanked),
+            versions::yank_reason.eq(reason),
             versions::updated_at.eq(chrono::Utc::now().naive_utc()),
         ))
         .get_result(conn)
         .await?;
     Ok(version)
 }
+
+pub async fn list_trusted_publishers(
+    conn: &mut AsyncPgConnection,
+    user: &User,
+) -> Result<Vec<(TrustedPublisher, Plugin)>> {
+    let publishers = trusted_publishers::table
+        .inner_join(plugins::table)
+        .filter(plugins::user_id.eq(user.id))
+        .order(trusted_publishers::created_at.desc())
+        .load(conn)
+        .await?;
+    Ok(publishers)
+}
+
+/// Trusted publishers of any plugin for the GitHub repository with the id
+/// `repository_id`, owned by `repository_owner_id`.
+pub async fn find_trusted_publishers(
+    conn: &mut AsyncPgConnection,
+    repository_id: &str,
+    repository_owner_id: &str,
+) -> Result<Vec<(TrustedPublisher, Plugin)>> {
+    let publishers = trusted_publishers::table
+        .inner_join(plugins::table)
+        .filter(trusted_publishers::repository_id.eq(repository_id))
+        .filter(trusted_publishers::repository_owner_id.eq(repository_owner_id))
+        .load(conn)
+        .await?;
+    Ok(publishers)
+}
+
+pub async fn insert_trusted_publisher(
+    conn: &mut AsyncPgConnection,
+    plugin: &Plugin,
+    repository: &GithubRepository,
+    workflow: &str,
+    environment: Option<&str>,
+) -> Result<TrustedPublisher> {
+    let publisher = diesel::insert_into(trusted_publishers::table)
+        .values((
+            trusted_publishers::plugin_id.eq(plugin.id),
+            trusted_publishers::repository.eq(repository.full_name.to_lowercase()),
+            trusted_publishers::repository_id.eq(repository.id.to_string()),
+            trusted_publishers::repository_owner_id.eq(repository.owner.id.to_string()),
+            trusted_publishers::workflow.eq(workflow),
+            trusted_publishers::environment.eq(environment),
+        ))
+        .get_result(conn)
+        .await?;
+    Ok(publisher)
+}
+
+/// Returns false if none of the user's plugins has a trusted publisher with that id.
+pub async fn delete_trusted_publisher(
+    conn: &mut AsyncPgConnection,
+    user: &User,
+    id: i32,
+) -> Result<bool> {
+    let plugin_ids = Plugin::belonging_to(user).select(plugins::id);
+    let deleted = diesel::delete(
+        trusted_publishers::table
+            .filter(trusted_publishers::id.eq(id))
+            .filter(trusted_publishers::plugin_id.eq_any(plugin_ids)),
+    )
+    .execute(conn)
+    .await?;
+    Ok(deleted > 0)
+}
+
+/// Deletes the plugin with its versions and trusted publishers, keeping its
+/// name reserved until `reserved_until`.
+pub async fn delete_plugin(
+    conn: &mut AsyncPgConnection,
+    plugin: &Plugin,
+    owner: &User,
+    deleted_by: &User,
+    reserved_until: NaiveDateTime,
+) -> Result<()> {
+    let plugin_id = plugin.id;
+    let user_id = owner.id;
+    let author = owner.gh_login.clone();
+    let name = plugin.name.clone();
+    let deleted_by = deleted_by.id;
+    conn.build_transaction()
+        .run(|conn| {
+            async move {
+                diesel::delete(api_tokens::table.filter(api_tokens::plugin_id.eq(plugin_id)))
+                    .execute(conn)
+                    .await?;
+                diesel::delete(
+                    trusted_publishers::table.filter(trusted_publishers::plugin_id.eq(plugin_id)),
+                )
+                .execute(conn)
+                .await?;
+                diesel::delete(plugin_owners::table.filter(plugin_owners::plugin_id.eq(plugin_id)))
+                    .execute(conn)
+                    .await?;
+                diesel::delete(
+                    version_downloads::table.filter(
+                        version_downloads::version_id.eq_any(
+                            versions::table
+                                .select(versions::id)
+                                .filter(versions::plugin_id.eq(plugin_id)),
+                        ),
+                    ),
+                )
+                .execute(conn)
+                .await?;
+                diesel::delete(versions::table.filter(versions::plugin_id.eq(plugin_id)))
+                    .execute(conn)
+                    .await?;
+                diesel::delete(plugins::table.find(plugin_id))
+                    .execute(conn)
+                    .await?;
+                diesel::insert_into(deleted_plugins::table)
+                    .values((
+                        deleted_plugins::user_id.eq(user_id),
+                        deleted_plugins::author.eq(author),
+                        deleted_plugins::name.eq(name),
+                        deleted_plugins::deleted_by.eq(deleted_by),
+                        deleted_plugins::reserved_until.eq(reserved_until),
+                    ))
+                    .execute(conn)
+                    .await?;
+                Ok(())
+            }
+            .boxed()
+        })
+        .await
+}
+
+/// The deletion still keeping `author/name` reserved, if any.
+pub async fn find_name_reservation(
+    conn: &mut AsyncPgConnection,
+    author: &str,
+    name: &str,
+) -> Result<Option<DeletedPlugin>> {
+    use diesel::dsl::now;
+
+    let reservation = deleted_plugins::table
+        .filter(deleted_plugins::author.eq(author))
+        .filter(deleted_plugins::name.eq(name))
+        .filter(deleted_plugins::reserved_until.gt(now))
+        .order(deleted_plugins::reserved_until.desc())
+        .first(conn)
+        .await
+        .optional()?;
+    Ok(reservation)
+}
+
+/// The user's plugins with all their versions, yanked ones included.
+pub async fn list_user_plugins(
+    conn: &mut AsyncPgConnection,
+    user: &User,
+) -> Result<Vec<(Plugin, Vec<Version>)>> {
+    let plugins: Vec<Plugin> = Plugin::belonging_to(user)
+        .order(plugins::name.asc())
+        .load(conn)
+        .await?;
+    let versions: Vec<Version> = Version::belonging_to(&plugins).load(conn).await?;
+    let versions = versions.grouped_by(&plugins);
+    Ok(plugins.into_iter().zip(versions).collect())
+}
+
+/// Whether the user can manage the plugin, as its author or as an owner
+/// added to it.
+pub async fn is_plugin_owner(
+    conn: &mut AsyncPgConnection,
+    plugin: &Plugin,
+    user: &User,
+) -> Result<bool> {
+    if plugin.user_id == user.id {
+        return Ok(true);
+    }
+    let owners: i64 = plugin_owners::table
+        .filter(plugin_owners::plugin_id.eq(plugin.id))
+        .filter(plugin_owners::user_id.eq(user.id))
+        .count()
+        .get_result(conn)
+        .await?;
+    Ok(owners > 0)
+}
+
+/// The plugin `author/name`, as long as the user owns it. Plugins the user
+/// doesn't own aren't found.
+pub async fn find_owned_plugin(
+    conn: &mut AsyncPgConnection,
+    user: &User,
+    author: &str,
+    name: &str,
+) -> Result<Plugin> {
+    let author = find_user_by_gh_login(conn, author).await?;
+    let plugin = find_plugin(conn, &author, name).await?;
+    if !is_plugin_owner(conn, &plugin, user).await? {
+        return Err(diesel::result::Error::NotFound.into());
+    }
+    Ok(plugin)
+}
+
+/// Plugins the user authored or was added as an owner of, with the login of
+/// their author, their versions and the logins of the owners added to them.
+pub async fn list_owned_plugins(
+    conn: &mut AsyncPgConnection,
+    user: &User,
+) -> Result<Vec<(Plugin, String, Vec<Version>, Vec<String>)>> {
+    let co_owned = plugin_owners::table
+        .select(plugin_owners::plugin_id)
+        .filter(plugin_owners::user_id.eq(user.id));
+    let plugins: Vec<Plugin> = plugins::table
+        .filter(
+            plugins::user_id
+                .eq(user.id)
+                .or(plugins::id.eq_any(co_owned)),
+        )
+        .order(plugins::name.asc())
+        .load(conn)
+        .await?;
+    let versions: Vec<Version> = Version::belonging_to(&plugins).load(conn).await?;
+    let versions = versions.grouped_by(&plugins);
+
+    let author_ids: Vec<i32> = plugins.iter().map(|plugin| plugin.user_id).collect();
+    let authors: BTreeMap<i32, String> = users::table
+        .filter(users::id.eq_any(author_ids))
+        .select((users::id, users::gh_login))
+        .load::<(i32, String)>(conn)
+        .await?
+        .into_iter()
+        .collect();
+
+    let plugin_ids: Vec<i32> = plugins.iter().map(|plugin| plugin.id).collect();
+    let mut owners: BTreeMap<i32, Vec<String>> = BTreeMap::new();
+    for (plugin_id, login) in plugin_owners::table
+        .inner_join(users::table)
+        .filter(plugin_owners::plugin_id.eq_any(plugin_ids))
+        .order(users::gh_login.asc())
+        .select((plugin_owners::plugin_id, users::gh_login))
+        .load::<(i32, String)>(conn)
+        .await?
+    {
+        owners.entry(plugin_id).or_default().push(login);
+    }
+
+    Ok(plugins
+        .into_iter()
+        .zip(versions)
+        .map(|(plugin, versions)| {
+            let author = authors.get(&plugin.user_id).cloned().unwrap_or_default();
+            let owners = owners.remove(&plugin.id).unwrap_or_default();
+            (plugin, author, versions, owners)
+        })
+        .collect())
+}
+
+/// Logins of the owners added to the plugin, leaving out its author.
+pub async fn list_plugin_owners(
+    conn: &mut AsyncPgConnection,
+    plugin: &Plugin,
+) -> Result<Vec<String>> {
+    let owners = plugin_owners::table
+        .inner_join(users::table)
+        .filter(plugin_owners::plugin_id.eq(plugin.id))
+        .order(users::gh_login.asc())
+        .select(users::gh_login)
+        .load(conn)
+        .await?;
+    Ok(owners)
+}
+
+pub async fn insert_plugin_owner(
+    conn: &mut AsyncPgConnection,
+    plugin: &Plugin,
+    owner: &User,
+) -> Result<()> {
+    diesel::insert_into(plugin_owners::table)
+        .values((
+            plugin_owners::plugin_id.eq(plugin.id),
+            plugin_owners::user_id.eq(owner.id),
+        ))
+        .on_conflict_do_nothing()
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Returns false if the user wasn't an owner added to the plugin.
+pub async fn delete_plugin_owner(
+    conn: &mut AsyncPgConnection,
+    plugin: &Plugin,
+    owner: &User,
+) -> Result<bool> {
+    let deleted = diesel::delete(
+        plugin_owners::table
+            .filter(plugin_owners::plugin_id.eq(plugin.id))
+            .filter(plugin_owners::user_id.eq(owner.id)),
+    )
+    .execute(conn)
+    .await?;
+    Ok(deleted > 0)
+}
+
+pub async fn modify_plugin_deprecation(
+    conn: &mut AsyncPgConnection,
+    plugin: &Plugin,
+    deprecated: bool,
+    message: Option<&str>,
+) -> Result<Plugin> {
+    let message = if deprecated { message } else { None };
+    let plugin = diesel::update(plugins::table.find(plugin.id))
+        .set((
+            plugins::deprecated.eq(deprecated),
+            plugins::deprecation_message.eq(message),
+            plugins::updated_at.eq(chrono::Utc::now().naive_utc()),
+        ))
+        .get_result(conn)
+        .await?;
+    Ok(plugin)
+}
+
+/// Daily downloads of the plugin's versions since `since`, as
+/// `(version, date, downloads)`.
+pub async fn list_version_downloads(
+    conn: &mut AsyncPgConnection,
+    plugin: &Plugin,
+    since: NaiveDate,
+) -> Result<Vec<(String, NaiveDate, i32)>> {
+    let downloads = version_downloads::table
+        .inner_join(versions::table)
+        .filter(versions::plugin_id.eq(plugin.id))
+        .filter(version_downloads::date.ge(since))
+        .select((
+            versions::num,
+            version_downloads::date,
+            version_downloads::downloads,
+        ))
+        .load(conn)
+        .await?;
+    Ok(downloads)
+}
+
+/// Adds downloads counted in memory, given as
+/// `(plugin_id, version_id, date, count)`. Versions deleted since they were
+/// downloaded are skipped.
+pub async fn add_downloads(
+    conn: &mut AsyncPgConnection,
+    downloads: &[(i32, i32, NaiveDate, i32)],
+) -> Result<()> {
+    // Rows are updated in id order so concurrent flushes can't deadlock
+    let mut plugin_counts: BTreeMap<i32, i32> = BTreeMap::new();
+    let mut version_counts: BTreeMap<i32, i32> = BTreeMap::new();
+    let mut daily_counts: BTreeMap<(i32, NaiveDate), i32> = BTreeMap::new();
+    for (plugin_id, version_id, date, count) in downloads {
+        *plugin_counts.entry(*plugin_id).or_default() += count;
+        *version_counts.entry(*version_id).or_default() += count;
+        *daily_counts.entry((*version_id, *date)).or_default() += count;
+    }
+
+    conn.build_transaction()
+        .run(|conn| {
+            async move {
+                for (plugin_id, count) in plugin_counts {
+                    diesel::update(plugins::table.find(plugin_id))
+                        .set(plugins::downloads.eq(plugins::downloads + count))
+                        .execute(conn)
+                        .await?;
+                }
+                let mut existing = Vec::new();
+                for (version_id, count) in version_counts {
+                    let updated = diesel::update(versions::table.find(version_id))
+                        .set(versions::downloads.eq(versions::downloads + count))
+                        .execute(conn)
+                        .await?;
+                    if updated > 0 {
+                        existing.push(version_id);
+                    }
+                }
+                for ((version_id, date), count) in daily_counts {
+                    if !existing.contains(&version_id) {
+                        continue;
+                    }
+                    diesel::insert_into(version_downloads::table)
+                        .values((
+                            version_downloads::version_id.eq(version_id),
+                            version_downloads::date.eq(date),
+                            version_downloads::downloads.eq(count),
+                        ))
+                        .on_conflict((version_downloads::version_id, version_downloads::date))
+                        .do_update()
+                        .set(version_downloads::downloads.eq(version_downloads::downloads + count))
+                        .execute(conn)
+                        .await?;
+                }
+                Ok(())
+            }
+            .boxed()
+        })
+        .await
+}
@@ -0,0 +1,243 @@
+//! Download counting. Downloads are added up in memory and written to Postgres
+//! in batches, so serving a download only needs the read pool and popular
+//! plugins don't contend on their row for every request.
+//!
+//! Since download counts rank search results, repeated downloads of a version
+//! from one IP address are only counted once per window, and the number of
+//! downloads per IP address can be limited. Both need `CLIENT_IP_HEADER`, see
+//! `client_ip`, and are off without it.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+    net::IpAddr,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use chrono::NaiveDate;
+
+use crate::db::{add_downloads, DbPool};
+
+const DEFAULT_FLUSH_INTERVAL_SECS: u64 = 10;
+const DEFAULT_DEDUP_WINDOW_SECS: u64 = 60 * 60;
+const DEFAULT_RATE_LIMIT_WINDOW_SECS: u64 = 60;
+/// Most entries kept at once, for deduplication and rate limiting each
+const DEFAULT_TRACKING_CAPACITY: usize = 100_000;
+
+fn env_or<T: FromStr>(name: &str, default: T) -> T {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+struct Limits {
+    dedup_window: Duration,
+    /// Downloads allowed per IP address in `rate_limit_window`
+    rate_limit: Option<u32>,
+    rate_limit_window: Duration,
+    capacity: usize,
+}
+
+impl Limits {
+    fn from_env() -> Self {
+        Self {
+            dedup_window: Duration::from_secs(env_or(
+                "DOWNLOAD_DEDUP_WINDOW_SECS",
+                DEFAULT_DEDUP_WINDOW_SECS,
+            )),
+            rate_limit: std::env::var("DOWNLOAD_RATE_LIMIT")
+                .ok()
+                .and_then(|limit| limit.parse().ok()),
+            rate_limit_window: Duration::from_secs(env_or(
+                "DOWNLOAD_RATE_LIMIT_WINDOW_SECS",
+                DEFAULT_RATE_LIMIT_WINDOW_SECS,
+            )),
+            capacity: env_or("DOWNLOAD_TRACKING_CAPACITY", DEFAULT_TRACKING_CAPACITY).max(1),
+        }
+    }
+}
+
+/// Entries with the time they were last set, holding at most `capacity`.
+/// Entries older than `window` are dropped, and once it's full the oldest
+/// entries make room for new ones, so many new clients at once can't reset
+/// what's tracked for everyone else.
+struct RecentMap<K, V> {
+    entries: HashMap<K, (Instant, V)>,
+    /// Keys in the order they were set. Keys set again since are left behind
+    /// with their old time and skipped.
+    order: VecDeque<(K, Instant)>,
+    capacity: usize,
+    window: Duration,
+}
+
+impl<K: Eq + Hash + Clone, V> RecentMap<K, V> {
+    fn new(capacity: usize, window: Duration) -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            capacity,
+            window,
+        }
+    }
+
+    /// The entry's value, if it was set within the window.
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let window = self.window;
+        self.entries
+            .get_mut(key)
+            .filter(|(set_at, _)| set_at.elapsed() < window)
+            .map(|(_, value)| value)
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        let now = Instant::now();
+        self.entries.insert(key.clone(), (now, value));
+        self.order.push_back((key, now));
+
+        while let Some((key, set_at)) = self.order.front() {
+            let stale = self.entries.get(key).map(|(t, _)| t) != Some(set_at);
+            let expired = set_at.elapsed() >= self.window;
+            if !stale && !expired && self.entries.len() <= self.capacity {
+                break;
+            }
+            let (key, _) = self.order.pop_front().unwrap();
+            if !stale {
+                self.entries.remove(&key);
+            }
+        }
+    }
+}
+
+#[derive(Hash, PartialEq, Eq, Clone, Copy)]
+struct DownloadKey {
+    plugin_id: i32,
+    version_id: i32,
+    date: NaiveDate,
+}
+
+/// Downloads that haven't been written to the database yet. Like the upload
+/// sessions they only live in this process, so the server flushes them on
+/// shutdown.
+#[derive(Clone)]
+pub struct DownloadCounter {
+    pending: Arc<Mutex<HashMap<DownloadKey, i32>>>,
+    /// When each IP address last had a download of a version counted
+    counted: Arc<Mutex<RecentMap<(IpAddr, i32), ()>>>,
+    /// Downloads of each IP address in its current rate limit window
+    requests: Arc<Mutex<RecentMap<IpAddr, u32>>>,
+    limits: Arc<Limits>,
+}
+
+impl Default for DownloadCounter {
+    fn default() -> Self {
+        DownloadCounter::new()
+    }
+}
+
+impl DownloadCounter {
+    /// Deduplication and rate limiting, once `CLIENT_IP_HEADER` is set, are
+    /// configured with `DOWNLOAD_DEDUP_WINDOW_SECS` (an hour by default, 0
+    /// counts every download), `DOWNLOAD_RATE_LIMIT` and `DOWNLOAD_RATE_LIMIT_WINDOW_SECS`
+    /// (no limit by default) and `DOWNLOAD_TRACKING_CAPACITY`.
+    pub fn new() -> Self {
+        let limits = Limits::from_env();
+        Self {
+            pending: Arc::default(),
+            counted: Arc::new(Mutex::new(RecentMap::new(
+                limits.capacity,
+                limits.dedup_window,
+            ))),
+            requests: Arc::new(Mutex::new(RecentMap::new(
+                limits.capacity,
+                limits.rate_limit_window,
+            ))),
+            limits: Arc::new(limits),
+        }
+    }
+
+    /// Whether `ip` is within the download rate limit, counting this request.
+    /// Clients without a known address aren't limited.
+    pub fn allow(&self, ip: Option<IpAddr>) -> bool {
+        let (limit, ip) = match (self.limits.rate_limit, ip) {
+            (Some(limit), Some(ip)) => (limit, ip),
+            _ => return true,
+        };
+        let mut requests = self.requests.lock().unwrap();
+        match requests.get_mut(&ip) {
+            Some(count) => {
+                *count += 1;
+                *count <= limit
+            }
+            None => {
+                requests.insert(ip, 1);
+                limit >= 1
+            }
+        }
+    }
+
+    /// Counts a download, unless `ip` downloaded the version recently.
+    /// Downloads from clients without a known address are always counted.
+    pub fn record(&self, plugin_id: i32, version_id: i32, ip: Option<IpAddr>) {
+        let window = self.limits.dedup_window;
+        if let Some(ip) = ip.filter(|_| !window.is_zero()) {
+            let mut counted = self.counted.lock().unwrap();
+            if counted.get_mut(&(ip, version_id)).is_some() {
+                return;
+            }
+            counted.insert((ip, version_id), ());
+        }
+
+        let key = DownloadKey {
+            plugin_id,
+            version_id,
+            date: chrono::Utc::now().naive_utc().date(),
+        };
+        *self.pending.lock().unwrap().entry(key).or_default() += 1;
+    }
+
+    /// Writes the buffered downloads, keeping them for the next flush if the
+    /// database can't be reached.
+    pub async fn flush(&self, db_pool: &DbPool) {
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+        if pending.is_empty() {
+            return;
+        }
+
+        let downloads: Vec<(i32, i32, NaiveDate, i32)> = pending
+            .iter()
+            .map(|(key, count)| (key.plugin_id, key.version_id, key.date, *count))
+            .collect();
+        let result = match db_pool.write.get().await {
+            Ok(mut conn) => add_downloads(&mut conn, &downloads).await,
+            Err(e) => Err(anyhow::anyhow!("{e}")),
+        };
+        if let Err(e) = result {
+            eprintln!("failed to write {} download counts: {e}", downloads.len());
+            let mut current = self.pending.lock().unwrap();
+            for (key, count) in pending {
+                *current.entry(key).or_default() += count;
+            }
+        }
+    }
+
+    /// Flushes every `DOWNLOAD_FLUSH_INTERVAL_SECS` seconds, 10 by default.
+    pub fn spawn_flush(&self, db_pool: DbPool) {
+        let counter = self.clone();
+        let secs = std::env::var("DOWNLOAD_FLUSH_INTERVAL_SECS")
+            .ok()
+            .and_then(|secs| secs.parse().ok())
+            .filter(|secs| *secs > 0)
+            .unwrap_or(DEFAULT_FLUSH_INTERVAL_SECS);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(secs));
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                counter.flush(&db_pool).await;
+            }
+        });
+    }
+}
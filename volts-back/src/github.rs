@@ -16,6 +16,35 @@ pub struct GithubUser {
     pub name: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct GithubRepository {
+    pub id: i64,
+    pub full_name: String,
+    pub owner: GithubOwner,
+    /// Permissions of the authenticated user, left out for anonymous requests
+    pub permissions: Option<GithubPermissions>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GithubOwner {
+    pub id: i64,
+    pub login: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GithubPermissions {
+    pub admin: bool,
+    pub push: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GithubOrgMembership {
+    pub state: String,
+    pub role: String,
+}
+
 #[derive(Clone)]
 pub struct GithubClient {
     base_url: String,
@@ -74,4 +103,78 @@ impl GithubClient {
     pub async fn current_user(&self, auth: &AccessToken) -> Result<GithubUser> {
         self.request("/user", auth).await
     }
+
+    pub async fn repository(
+        &self,
+        owner: &str,
+        repo: &str,
+        auth: &AccessToken,
+    ) -> Result<GithubRepository> {
+        self.request(&format!("/repos/{owner}/{repo}"), auth).await
+    }
+
+    /// Needs the `read:org` scope.
+    pub async fn org_membership(
+        &self,
+        org: &str,
+        auth: &AccessToken,
+    ) -> Result<GithubOrgMembership> {
+        self.request(&format!("/user/memberships/orgs/{org}"), auth)
+            .await
+    }
+
+    /// Whether the authenticated user can push to the repository, or owns the
+    /// organization it belongs to.
+    pub async fn can_publish_from(&self, owner: &str, repo: &str, auth: &AccessToken) -> bool {
+        match self.repository(owner, repo, auth).await {
+            Ok(repository) => self.has_publish_access(&repository, auth).await,
+            Err(_) => false,
+        }
+    }
+
+    /// [`GithubClient::can_publish_from`] for a repository already looked up
+    /// with the same access token.
+    pub async fn has_publish_access(
+        &self,
+        repository: &GithubRepository,
+        auth: &AccessToken,
+    ) -> bool {
+        if let Some(permissions) = repository.permissions.as_ref() {
+            if permissions.push || permissions.admin {
+                return true;
+            }
+        }
+        if repository.owner.kind != "Organization" {
+            return false;
+        }
+        match self.org_membership(&repository.owner.login, auth).await {
+            Ok(membership) => membership.state == "active" && membership.role == "admin",
+            Err(_) => false,
+        }
+    }
+}
+
+/// Owner and name of a GitHub repository URL such as
+/// `https://github.com/lapce/lapce` or `https://github.com/lapce/lapce.git`.
+pub fn parse_repository_url(url: &str) -> Option<(&str, &str)> {
+    let url = url.trim();
+    let path = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))?;
+    let path = path.strip_prefix("www.").unwrap_or(path);
+    let path = path.strip_prefix("github.com/")?;
+
+    let mut segments = path.split('/');
+    let owner = segments.next()?;
+    let repo = segments.next()?;
+    let repo = repo.strip_suffix(".git").unwrap_or(repo);
+    let valid = |s: &str| {
+        !s.is_empty()
+            && s.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+    };
+    if !valid(owner) || !valid(repo) {
+        return None;
+    }
+    Some((owner, repo))
 }
@@ -1,11 +1,26 @@
 use std::net::SocketAddr;
 
+use axum::extract::FromRef;
+use db::DbPool;
+use downloads::DownloadCounter;
+use state::AppState;
+use upload::UploadSessions;
+
+pub(crate) mod cache;
+pub(crate) mod changelog;
 pub(crate) mod db;
+pub(crate) mod downloads;
 pub mod github;
+pub(crate) mod manage;
 pub(crate) mod plugin;
+pub(crate) mod readme;
 pub mod router;
 pub mod state;
+pub(crate) mod storage;
 pub mod token;
+pub mod trusted;
+pub(crate) mod upload;
+pub(crate) mod user;
 pub mod util;
 
 #[macro_use]
@@ -13,10 +28,39 @@ extern crate diesel;
 
 pub async fn start_server() {
     dotenvy::dotenv().ok();
-    let router = crate::router::build_router();
+    let state = AppState::new();
+    let db_pool = DbPool::from_ref(&state);
+    let downloads = DownloadCounter::from_ref(&state);
+    downloads.spawn_flush(db_pool.clone());
+    UploadSessions::from_ref(&state).spawn_sweep();
+
+    let router = crate::router::build_router(state);
     let addr = SocketAddr::from(([0, 0, 0, 0], 8080));
     axum::Server::bind(&addr)
         .serve(router.into_make_service())
+        .with_graceful_shutdown(shutdown_signal())
         .await
         .unwrap();
+
+    downloads.flush(&db_pool).await;
+}
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c().await.unwrap();
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .unwrap()
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
 }
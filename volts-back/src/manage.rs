@@ -0,0 +1,306 @@
+//! Plugin management for the web frontend, authenticated with the session
+//! cookie rather than API tokens. Besides its author, the owners added to a
+//! plugin can manage it here. Publishing stays with the author, since plugins
+//! are published under their author's login.
+
+use async_session::MemoryStore;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json, TypedHeader,
+};
+use volts_core::{
+    db::models::{Plugin, User},
+    DashboardPlugin, DashboardPluginList, DeprecatePayload, NewOwnerPayload, PluginOwnerList,
+    YankPayload,
+};
+
+use crate::{
+    db::{
+        delete_plugin_owner, find_owned_plugin, find_user_by_gh_login, insert_plugin_owner,
+        list_owned_plugins, list_plugin_owners, modify_plugin_deprecation,
+        modify_plugin_version_yank, DbPool,
+    },
+    plugin::{encode_version, yank_reason},
+    router::authenticated_user,
+};
+
+/// Longest message a plugin can be deprecated with
+const MAX_DEPRECATION_MESSAGE_LEN: usize = 200;
+
+async fn session_user(
+    store: MemoryStore,
+    db_pool: &DbPool,
+    cookies: Option<TypedHeader<headers::Cookie>>,
+) -> Result<User, Response> {
+    let user = match cookies {
+        Some(cookies) => authenticated_user(State(store), State(db_pool.clone()), cookies).await,
+        None => None,
+    };
+    user.ok_or_else(|| (StatusCode::UNAUTHORIZED, "not logged in").into_response())
+}
+
+/// The plugin `author/name`, as long as the user owns it.
+async fn owned_plugin(
+    db_pool: &DbPool,
+    user: &User,
+    author: &str,
+    name: &str,
+) -> Result<Plugin, Response> {
+    let mut conn = db_pool.read.get().await.unwrap();
+    find_owned_plugin(&mut conn, user, author, &name.to_lowercase())
+        .await
+        .map_err(|_| (StatusCode::NOT_FOUND, "plugin not found").into_response())
+}
+
+pub async fn yank(
+    State(store): State<MemoryStore>,
+    State(db_pool): State<DbPool>,
+    cookies: Option<TypedHeader<headers::Cookie>>,
+    Path((author, name, version)): Path<(String, String, String)>,
+    payload: Option<Json<YankPayload>>,
+) -> impl IntoResponse {
+    let user = match session_user(store, &db_pool, cookies).await {
+        Ok(user) => user,
+        Err(resp) => return resp,
+    };
+    let reason = match yank_reason(payload) {
+        Ok(reason) => reason,
+        Err(resp) => return resp,
+    };
+
+    modify_yank(
+        &user,
+        &db_pool,
+        &author,
+        &name,
+        &version,
+        true,
+        reason.as_deref(),
+    )
+    .await
+}
+
+pub async fn unyank(
+    State(store): State<MemoryStore>,
+    State(db_pool): State<DbPool>,
+    cookies: Option<TypedHeader<headers::Cookie>>,
+    Path((author, name, version)): Path<(String, String, String)>,
+) -> impl IntoResponse {
+    let user = match session_user(store, &db_pool, cookies).await {
+        Ok(user) => user,
+        Err(resp) => return resp,
+    };
+
+    modify_yank(&user, &db_pool, &author, &name, &version, false, None).await
+}
+
+async fn modify_yank(
+    user: &User,
+    db_pool: &DbPool,
+    author: &str,
+    name: &str,
+    version: &str,
+    yanked: bool,
+    reason: Option<&str>,
+) -> Response {
+    let plugin = match owned_plugin(db_pool, user, author, name).await {
+        Ok(plugin) => plugin,
+        Err(resp) => return resp,
+    };
+    let mut conn = db_pool.write.get().await.unwrap();
+    if let Err(e) = modify_plugin_version_yank(&mut conn, &plugin, version, yanked, reason).await {
+        return (StatusCode::BAD_REQUEST, e.to_string()).into_response();
+    }
+    ().into_response()
+}
+
+/// The plugins the signed in user owns for the account dashboard.
+pub async fn plugins(
+    State(store): State<MemoryStore>,
+    State(db_pool): State<DbPool>,
+    cookies: Option<TypedHeader<headers::Cookie>>,
+) -> impl IntoResponse {
+    let user = match session_user(store, &db_pool, cookies).await {
+        Ok(user) => user,
+        Err(resp) => return resp,
+    };
+
+    let mut conn = db_pool.read.get().await.unwrap();
+    let plugins = list_owned_plugins(&mut conn, &user)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(plugin, author, versions, owners)| {
+            let mut versions: Vec<(Option<semver::Version>, _)> = versions
+                .into_iter()
+                .map(|v| (semver::Version::parse(&v.num).ok(), v))
+                .collect();
+            versions.sort_by(|(a, _), (b, _)| b.cmp(a));
+            let latest = versions
+                .iter()
+                .find(|(num, v)| num.is_some() && !v.yanked)
+                .map(|(_, v)| v.num.clone());
+            DashboardPlugin {
+                author,
+                name: plugin.name,
+                display_name: plugin.display_name,
+                description: plugin.description,
+                downloads: plugin.downloads,
+                latest,
+                deprecated: plugin.deprecated,
+                deprecation_message: plugin.deprecation_message,
+                updated_at: plugin.updated_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+                versions: versions
+                    .into_iter()
+                    .map(|(_, v)| encode_version(v))
+                    .collect(),
+                owners,
+            }
+        })
+        .collect();
+    Json(DashboardPluginList { plugins }).into_response()
+}
+
+pub async fn deprecate(
+    State(store): State<MemoryStore>,
+    State(db_pool): State<DbPool>,
+    cookies: Option<TypedHeader<headers::Cookie>>,
+    Path((author, name)): Path<(String, String)>,
+    payload: Option<Json<DeprecatePayload>>,
+) -> impl IntoResponse {
+    let user = match session_user(store, &db_pool, cookies).await {
+        Ok(user) => user,
+        Err(resp) => return resp,
+    };
+    let message = payload
+        .and_then(|Json(payload)| payload.message)
+        .map(|message| message.trim().to_string())
+        .filter(|message| !message.is_empty());
+    if let Some(message) = message.as_ref() {
+        if message.chars().count() > MAX_DEPRECATION_MESSAGE_LEN {
+            return (
+                StatusCode::BAD_REQUEST,
+                format!(
+                    "deprecation message can't be longer than {MAX_DEPRECATION_MESSAGE_LEN} characters"
+                ),
+            )
+                .into_response();
+        }
+    }
+
+    modify_deprecation(&user, &db_pool, &author, &name, true, message.as_deref()).await
+}
+
+pub async fn undeprecate(
+    State(store): State<MemoryStore>,
+    State(db_pool): State<DbPool>,
+    cookies: Option<TypedHeader<headers::Cookie>>,
+    Path((author, name)): Path<(String, String)>,
+) -> impl IntoResponse {
+    let user = match session_user(store, &db_pool, cookies).await {
+        Ok(user) => user,
+        Err(resp) => return resp,
+    };
+
+    modify_deprecation(&user, &db_pool, &author, &name, false, None).await
+}
+
+async fn modify_deprecation(
+    user: &User,
+    db_pool: &DbPool,
+    author: &str,
+    name: &str,
+    deprecated: bool,
+    message: Option<&str>,
+) -> Response {
+    let plugin = match owned_plugin(db_pool, user, author, name).await {
+        Ok(plugin) => plugin,
+        Err(resp) => return resp,
+    };
+    let mut conn = db_pool.write.get().await.unwrap();
+    modify_plugin_deprecation(&mut conn, &plugin, deprecated, message)
+        .await
+        .unwrap();
+    ().into_response()
+}
+
+/// Adds a user as an owner of the plugin. Owners are added by their GitHub
+/// login, and have to have signed in to the registry once.
+pub async fn add_owner(
+    State(store): State<MemoryStore>,
+    State(db_pool): State<DbPool>,
+    cookies: Option<TypedHeader<headers::Cookie>>,
+    Path((author, name)): Path<(String, String)>,
+    Json(payload): Json<NewOwnerPayload>,
+) -> impl IntoResponse {
+    let user = match session_user(store, &db_pool, cookies).await {
+        Ok(user) => user,
+        Err(resp) => return resp,
+    };
+    let plugin = match owned_plugin(&db_pool, &user, &author, &name).await {
+        Ok(plugin) => plugin,
+        Err(resp) => return resp,
+    };
+
+    let login = payload.login.trim().trim_start_matches('@');
+    let mut conn = db_pool.write.get().await.unwrap();
+    let owner = match find_user_by_gh_login(&mut conn, login).await {
+        Ok(owner) => owner,
+        Err(_) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                format!(
+                    "{login} has to sign in to the registry once before being added as an owner"
+                ),
+            )
+                .into_response()
+        }
+    };
+    if owner.id == plugin.user_id {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("{login} is the author of the plugin"),
+        )
+            .into_response();
+    }
+    insert_plugin_owner(&mut conn, &plugin, &owner)
+        .await
+        .unwrap();
+
+    let owners = list_plugin_owners(&mut conn, &plugin).await.unwrap();
+    Json(PluginOwnerList { owners }).into_response()
+}
+
+/// Removes an owner added to the plugin, the signed in user included. The
+/// author can't be removed.
+pub async fn remove_owner(
+    State(store): State<MemoryStore>,
+    State(db_pool): State<DbPool>,
+    cookies: Option<TypedHeader<headers::Cookie>>,
+    Path((author, name, login)): Path<(String, String, String)>,
+) -> impl IntoResponse {
+    let user = match session_user(store, &db_pool, cookies).await {
+        Ok(user) => user,
+        Err(resp) => return resp,
+    };
+    let plugin = match owned_plugin(&db_pool, &user, &author, &name).await {
+        Ok(plugin) => plugin,
+        Err(resp) => return resp,
+    };
+
+    let mut conn = db_pool.write.get().await.unwrap();
+    let removed = match find_user_by_gh_login(&mut conn, &login).await {
+        Ok(owner) => delete_plugin_owner(&mut conn, &plugin, &owner)
+            .await
+            .unwrap(),
+        Err(_) => false,
+    };
+    if !removed {
+        return (StatusCode::NOT_FOUND, "owner not found").into_response();
+    }
+
+    let owners = list_plugin_owners(&mut conn, &plugin).await.unwrap();
+    Json(PluginOwnerList { owners }).into_response()
+}
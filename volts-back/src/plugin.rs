@@ -5,20 +5,21 @@ use std::{
 
 use anyhow::Result;
 use axum::{
-    body::Bytes,
-    extract::{BodyStream, Path, Query, State},
-    http::StatusCode,
-    response::IntoResponse,
+    body::{Body, Bytes},
+    extract::{BodyStream, FromRequest, Multipart, Path, Query, State},
+    http::{header, HeaderMap, Request, StatusCode},
+    response::{IntoResponse, Response},
     BoxError, Json, TypedHeader,
 };
 use diesel::{BelongingToDsl, BoolExpressionMethods, ExpressionMethods, GroupedBy};
 use diesel::{PgTextExpressionMethods, QueryDsl};
-use diesel_async::RunQueryDsl;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
 use futures::{FutureExt, Stream, TryStreamExt};
 use headers::authorization::Bearer;
 use lapce_rpc::plugin::VoltMetadata;
-use s3::Bucket;
+use oauth2::AccessToken;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use tar::Archive;
 use tokio_util::io::StreamReader;
 use toml_edit::easy as toml;
@@ -27,18 +28,36 @@ use volts_core::{
         models::{Plugin, User, Version},
         schema::{plugins, users, versions},
     },
-    EncodePlugin, PluginList,
+    DownloadStats, EncodePlugin, EncodeVersion, PluginDeleted, PluginList, PluginUpdate,
+    ReleaseNotes, UpdateCheckList, UpdateCheckPayload, VersionDownloads, VersionList, YankPayload,
+    TOKEN_SCOPE_DELETE, TOKEN_SCOPE_PUBLISH, TOKEN_SCOPE_YANK,
 };
 use zstd::{Decoder, Encoder};
 
-use crate::db::{
-    find_api_token, find_plugin, find_plugin_version, find_user, find_user_by_gh_login,
-    modify_plugin_version_yank, DbPool, NewPlugin, NewVersion,
+use crate::{
+    cache, changelog,
+    db::{
+        delete_plugin, find_api_token, find_name_reservation, find_plugin, find_plugin_version,
+        find_user, find_user_by_gh_login, list_version_downloads, modify_plugin_version_yank,
+        DbPool, NewPlugin, NewVersion,
+    },
+    downloads::DownloadCounter,
+    github::{parse_repository_url, GithubClient},
+    readme,
+    storage::{ArchiveMode, Storage},
+    util::{client_ip, is_admin, public_url},
 };
 
 const VOLT_MANIFEST: &str = "volt.toml";
 const VOLT_ARCHIVE: &str = "plugin.volt";
 const OLD_VOLT_ARCHIVE: &str = "volt.tar.gz";
+const CHANGELOG: &str = "CHANGELOG.md";
+const README_HTML_CONTENT_TYPE: &str = "text/html; charset=utf-8";
+/// Most files from the archive a README can show
+const MAX_README_FILES: usize = 50;
+const MAX_README_FILE_SIZE: u64 = 5 * 1024 * 1024;
+/// Length of the daily download series served to the plugin page
+const DOWNLOAD_STATS_DAYS: i64 = 90;
 
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
@@ -58,6 +77,7 @@ struct IconThemeConfig {
 pub struct SearchQuery {
     q: Option<String>,
     sort: Option<String>,
+    kind: Option<String>,
     limit: Option<usize>,
     offset: Option<usize>,
 }
@@ -77,8 +97,13 @@ pub async fn search(
                 .filter(versions::yanked.eq(false)),
         ))
         .into_boxed();
-    let mut total: i64 = 0;
-    let mut had_query = false;
+    let mut total_query = plugins::table
+        .filter(diesel::expression::exists::exists(
+            versions::table
+                .filter(versions::plugin_id.eq(plugins::id))
+                .filter(versions::yanked.eq(false)),
+        ))
+        .into_boxed();
     if let Some(q) = query.q.as_ref() {
         if !q.is_empty() {
             let q = format!("%{q}%");
@@ -88,33 +113,21 @@ pub async fn search(
                 .or(plugins::description.ilike(q.clone()))
                 .or(plugins::display_name.ilike(q));
             sql_query = sql_query.filter(filter.clone());
-
-            had_query = true;
-            total = plugins::table
-                .filter(filter)
-                .filter(diesel::expression::exists::exists(
-                    versions::table
-                        .filter(versions::plugin_id.eq(plugins::id))
-                        .filter(versions::yanked.eq(false)),
-                ))
-                .count()
-                .get_result(&mut conn)
-                .await
-                .unwrap();
+            total_query = total_query.filter(filter);
         }
     }
-    if !had_query {
-        total = plugins::table
-            .filter(diesel::expression::exists::exists(
-                versions::table
-                    .filter(versions::plugin_id.eq(plugins::id))
-                    .filter(versions::yanked.eq(false)),
-            ))
-            .count()
-            .get_result(&mut conn)
-            .await
-            .unwrap();
+    match query.kind.as_deref() {
+        Some("wasm") => {
+            sql_query = sql_query.filter(plugins::wasm.eq(true));
+            total_query = total_query.filter(plugins::wasm.eq(true));
+        }
+        Some("theme") => {
+            sql_query = sql_query.filter(plugins::wasm.eq(false));
+            total_query = total_query.filter(plugins::wasm.eq(false));
+        }
+        _ => {}
     }
+    let total: i64 = total_query.count().get_result(&mut conn).await.unwrap();
 
     sql_query = sql_query.offset(offset as i64).limit(limit as i64);
     match query.sort.as_deref() {
@@ -147,23 +160,7 @@ pub async fn search(
 
     let plugins: Vec<EncodePlugin> = versions
         .zip(data)
-        .filter_map(|(v, (p, u))| {
-            let version = v?.1;
-            Some(EncodePlugin {
-                id: p.id,
-                name: p.name,
-                author: u.gh_login,
-                version: version.num,
-                display_name: p.display_name,
-                description: p.description,
-                downloads: p.downloads,
-                repository: p.repository,
-                updated_at_ts: p.updated_at.timestamp(),
-                updated_at: p.updated_at.format("%Y-%m-%d %H:%M:%S").to_string(),
-                released_at: version.created_at.format("%Y-%m-%d %H:%M:%S").to_string(),
-                wasm: p.wasm,
-            })
-        })
+        .filter_map(|(v, (p, u))| Some(encode_plugin(p, u.gh_login, v?.1)))
         .collect();
 
     Json(PluginList {
@@ -175,16 +172,19 @@ pub async fn search(
 }
 
 pub async fn meta(
-    State(bucket): State<Bucket>,
     State(db_pool): State<DbPool>,
+    headers: HeaderMap,
     Path((author, name, version)): Path<(String, String, String)>,
 ) -> impl IntoResponse {
     let mut conn = db_pool.read.get().await.unwrap();
-    let user = find_user_by_gh_login(&mut conn, &author).await.unwrap();
     let name = name.to_lowercase();
-    let plugin = find_plugin(&mut conn, &user, &name).await.unwrap();
+    let (_, plugin) = match lookup_plugin(&mut conn, &author, &name).await {
+        Ok(found) => found,
+        Err(resp) => return resp,
+    };
 
-    let version = if version == "latest" {
+    let latest = version == "latest";
+    let version = if latest {
         let versions: Vec<Version> = Version::belonging_to(&plugin)
             .filter(versions::yanked.eq(false))
             .load(&mut conn)
@@ -199,151 +199,531 @@ pub async fn meta(
                     .map(|version| (v, version))
             })
             .max_by_key(|(_, version)| version.clone());
-        max.unwrap().0
+        match max {
+            Some((v, _)) => v,
+            None => return (StatusCode::NOT_FOUND, "no version available").into_response(),
+        }
     } else {
-        find_plugin_version(&mut conn, &plugin, &version)
-            .await
-            .unwrap()
+        match lookup_version(&mut conn, &plugin, &version).await {
+            Ok(version) => version,
+            Err(resp) => return resp,
+        }
+    };
+
+    let plugin = encode_plugin(plugin, author, version);
+    let etag = cache::etag(&[&serde_json::to_vec(&plugin).unwrap()]);
+    let cache_control = if latest {
+        cache::SHORT_LIVED
+    } else {
+        cache::IMMUTABLE
     };
+    cache::cached(&headers, &etag, cache_control, Json(plugin))
+}
+
+/// Files of a version are only written when it's published, and a version
+/// can't be published again, so they're identified by the version row and
+/// the archive's checksum.
+fn version_file_etag(version: &Version, file: &str) -> String {
+    cache::etag(&[
+        file.as_bytes(),
+        &version.id.to_le_bytes(),
+        version.checksum.as_deref().unwrap_or_default().as_bytes(),
+        &version.created_at.timestamp_nanos().to_le_bytes(),
+    ])
+}
 
-    Json(EncodePlugin {
+pub(crate) fn encode_plugin(plugin: Plugin, author: String, version: Version) -> EncodePlugin {
+    EncodePlugin {
         id: plugin.id,
-        name,
+        name: plugin.name,
         author,
         version: version.num,
         display_name: plugin.display_name,
         description: plugin.description,
         downloads: plugin.downloads,
         repository: plugin.repository,
+        repository_verified: plugin.repository_verified,
         updated_at_ts: plugin.updated_at.timestamp(),
         updated_at: plugin.updated_at.format("%Y-%m-%d %H:%M:%S").to_string(),
         released_at: version.created_at.format("%Y-%m-%d %H:%M:%S").to_string(),
         wasm: plugin.wasm,
-    })
+        checksum: version.checksum,
+        deprecated: plugin.deprecated,
+        deprecation_message: plugin.deprecation_message,
+    }
 }
 
-pub async fn download(
-    State(bucket): State<Bucket>,
+pub async fn versions(
     State(db_pool): State<DbPool>,
-    Path((author, name, version)): Path<(String, String, String)>,
+    Path((author, name)): Path<(String, String)>,
 ) -> impl IntoResponse {
     let mut conn = db_pool.read.get().await.unwrap();
-    let user = find_user_by_gh_login(&mut conn, &author).await.unwrap();
     let name = name.to_lowercase();
-    let plugin = find_plugin(&mut conn, &user, &name).await.unwrap();
-    let version = find_plugin_version(&mut conn, &plugin, &version)
+    let (_, plugin) = match lookup_plugin(&mut conn, &author, &name).await {
+        Ok(found) => found,
+        Err(resp) => return resp,
+    };
+
+    let versions: Vec<Version> = Version::belonging_to(&plugin)
+        .load(&mut conn)
         .await
         .unwrap();
-    {
-        let mut conn = db_pool.write.get().await.unwrap();
-        diesel::update(plugins::dsl::plugins.find(plugin.id))
-            .set(plugins::downloads.eq(plugins::downloads + 1))
-            .execute(&mut conn)
-            .await
-            .unwrap();
-        diesel::update(versions::dsl::versions.find(version.id))
-            .set(versions::downloads.eq(versions::downloads + 1))
-            .execute(&mut conn)
+    let mut versions: Vec<(semver::Version, Version)> = versions
+        .into_iter()
+        .filter_map(|v| Some((semver::Version::parse(&v.num).ok()?, v)))
+        .collect();
+    versions.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+    Json(VersionList {
+        versions: versions
+            .into_iter()
+            .map(|(_, v)| encode_version(v))
+            .collect(),
+    })
+    .into_response()
+}
+
+pub(crate) fn encode_version(version: Version) -> EncodeVersion {
+    EncodeVersion {
+        num: version.num,
+        yanked: version.yanked,
+        downloads: version.downloads,
+        released_at: version.created_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+        checksum: version.checksum,
+        yank_reason: version.yank_reason,
+        release_notes: version.release_notes,
+    }
+}
+
+pub async fn updates(
+    State(db_pool): State<DbPool>,
+    Json(payload): Json<UpdateCheckPayload>,
+) -> impl IntoResponse {
+    if payload.plugins.len() > 100 {
+        return (
+            StatusCode::BAD_REQUEST,
+            "can't check more than 100 plugins at once",
+        )
+            .into_response();
+    }
+
+    let mut conn = db_pool.read.get().await.unwrap();
+    let mut updates = Vec::new();
+    for id in payload.plugins {
+        let user = match find_user_by_gh_login(&mut conn, &id.author).await {
+            Ok(user) => user,
+            Err(_) => continue,
+        };
+        let name = id.name.to_lowercase();
+        let plugin = match find_plugin(&mut conn, &user, &name).await {
+            Ok(plugin) => plugin,
+            Err(_) => continue,
+        };
+        let installed = match semver::Version::parse(&id.version) {
+            Ok(installed) => installed,
+            Err(_) => continue,
+        };
+
+        let versions: Vec<Version> = Version::belonging_to(&plugin)
+            .load(&mut conn)
             .await
             .unwrap();
+        let installed_version = versions.iter().find(|v| v.num == id.version);
+        let yanked = installed_version.map(|v| v.yanked).unwrap_or(false);
+        let yank_reason = installed_version.and_then(|v| v.yank_reason.clone());
+        let mut newer: Vec<(semver::Version, Version)> = versions
+            .into_iter()
+            .filter(|v| !v.yanked)
+            .filter_map(|v| Some((semver::Version::parse(&v.num).ok()?, v)))
+            .filter(|(version, _)| version > &installed)
+            // Pre-releases are only offered to those already on one
+            .filter(|(version, _)| version.pre.is_empty() || !installed.pre.is_empty())
+            .collect();
+        newer.sort_by(|(a, _), (b, _)| b.cmp(a));
+        let release_notes = newer
+            .iter()
+            .filter_map(|(_, v)| {
+                Some(ReleaseNotes {
+                    num: v.num.clone(),
+                    notes: v.release_notes.clone()?,
+                })
+            })
+            .collect();
+        let latest = newer.into_iter().next().map(|(_, v)| encode_version(v));
+
+        updates.push(PluginUpdate {
+            author: id.author,
+            name,
+            version: id.version,
+            yanked,
+            yank_reason,
+            latest,
+            release_notes,
+        });
+    }
+
+    Json(UpdateCheckList { plugins: updates }).into_response()
+}
+
+pub async fn download(
+    State(storage): State<Storage>,
+    State(db_pool): State<DbPool>,
+    State(downloads): State<DownloadCounter>,
+    headers: HeaderMap,
+    Path((author, name, version)): Path<(String, String, String)>,
+) -> impl IntoResponse {
+    let ip = client_ip(&headers);
+    if !downloads.allow(ip) {
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            "too many downloads, try again later",
+        )
+            .into_response();
+    }
+
+    let mut conn = db_pool.read.get().await.unwrap();
+    let name = name.to_lowercase();
+    let (user, plugin) = match lookup_plugin(&mut conn, &author, &name).await {
+        Ok(found) => found,
+        Err(resp) => return resp,
+    };
+    let version = match lookup_version(&mut conn, &plugin, &version).await {
+        Ok(version) => version,
+        Err(resp) => return resp,
+    };
+    downloads.record(plugin.id, version.id, ip);
+
+    // The body is a URL to download the archive from, which clients expect in
+    // either mode
+    match storage.archive_mode {
+        ArchiveMode::Presigned => {
+            let s3_path = archive_path(&storage, &user.gh_login, &name, &version.num).await;
+            storage.presigned_url(&s3_path).into_response()
+        }
+        ArchiveMode::Stream => public_url(
+            &headers,
+            &format!(
+                "/api/v1/plugins/{}/{name}/{}/archive",
+                user.gh_login, version.num
+            ),
+        )
+        .into_response(),
+    }
+}
+
+/// Streams the archive of a version through the backend. Downloads are
+/// counted by `download`, which links here when `ARCHIVE_SERVE_MODE` is
+/// `stream`.
+pub async fn archive(
+    State(storage): State<Storage>,
+    State(db_pool): State<DbPool>,
+    headers: HeaderMap,
+    Path((author, name, version)): Path<(String, String, String)>,
+) -> impl IntoResponse {
+    let mut conn = db_pool.read.get().await.unwrap();
+    let name = name.to_lowercase();
+    let (user, plugin) = match lookup_plugin(&mut conn, &author, &name).await {
+        Ok(found) => found,
+        Err(resp) => return resp,
+    };
+    let version = match lookup_version(&mut conn, &plugin, &version).await {
+        Ok(version) => version,
+        Err(resp) => return resp,
+    };
+    drop(conn);
+
+    let s3_path = archive_path(&storage, &user.gh_login, &name, &version.num).await;
+    storage.serve_archive(&s3_path, &headers).await
+}
+
+/// `404` when the row doesn't exist, and `500` for other database errors.
+fn lookup_error(e: anyhow::Error, what: &str) -> Response {
+    match e.downcast_ref::<diesel::result::Error>() {
+        Some(diesel::result::Error::NotFound) => {
+            (StatusCode::NOT_FOUND, format!("{what} not found")).into_response()
+        }
+        _ => (StatusCode::INTERNAL_SERVER_ERROR, "database error").into_response(),
     }
+}
 
-    let s3_path = format!("{}/{}/{}/{VOLT_ARCHIVE}", user.gh_login, name, version.num);
-    if bucket
-        .head_object(&s3_path)
+/// The author and plugin of a public route, with `name` in lowercase.
+async fn lookup_plugin(
+    conn: &mut AsyncPgConnection,
+    author: &str,
+    name: &str,
+) -> Result<(User, Plugin), Response> {
+    let user = find_user_by_gh_login(conn, author)
         .await
-        .map(|(_, code)| code == 200)
-        .unwrap_or(false)
-    {
-        bucket.presign_get(&s3_path, 60, None).unwrap()
+        .map_err(|e| lookup_error(e, "plugin"))?;
+    let plugin = find_plugin(conn, &user, name)
+        .await
+        .map_err(|e| lookup_error(e, "plugin"))?;
+    Ok((user, plugin))
+}
+
+async fn lookup_version(
+    conn: &mut AsyncPgConnection,
+    plugin: &Plugin,
+    num: &str,
+) -> Result<Version, Response> {
+    find_plugin_version(conn, plugin, num)
+        .await
+        .map_err(|e| lookup_error(e, "version"))
+}
+
+/// Versions published before archives were zstd compressed only have the old
+/// archive.
+async fn archive_path(storage: &Storage, author: &str, name: &str, num: &str) -> String {
+    let s3_path = format!("{author}/{name}/{num}/{VOLT_ARCHIVE}");
+    if storage.exists(&s3_path).await {
+        s3_path
     } else {
-        let old_s3_path = format!(
-            "{}/{}/{}/{OLD_VOLT_ARCHIVE}",
-            user.gh_login, name, version.num
-        );
-        bucket.presign_get(&old_s3_path, 60, None).unwrap()
+        format!("{author}/{name}/{num}/{OLD_VOLT_ARCHIVE}")
     }
 }
 
-pub async fn readme(
-    State(bucket): State<Bucket>,
+pub async fn downloads(
     State(db_pool): State<DbPool>,
-    Path((author, name, version)): Path<(String, String, String)>,
+    Path((author, name)): Path<(String, String)>,
 ) -> impl IntoResponse {
     let mut conn = db_pool.read.get().await.unwrap();
-    let user = find_user_by_gh_login(&mut conn, &author).await.unwrap();
     let name = name.to_lowercase();
-    let plugin = find_plugin(&mut conn, &user, &name).await.unwrap();
-    let version = find_plugin_version(&mut conn, &plugin, &version)
+    let (_, plugin) = match lookup_plugin(&mut conn, &author, &name).await {
+        Ok(found) => found,
+        Err(resp) => return resp,
+    };
+
+    let today = chrono::Utc::now().naive_utc().date();
+    let since = today - chrono::Duration::days(DOWNLOAD_STATS_DAYS - 1);
+    let downloads = list_version_downloads(&mut conn, &plugin, since)
         .await
         .unwrap();
+
+    let mut series: HashMap<String, Vec<i32>> = HashMap::new();
+    for (num, date, count) in downloads {
+        let day = (date - since).num_days() as usize;
+        if let Some(count_on_day) = series
+            .entry(num)
+            .or_insert_with(|| vec![0; DOWNLOAD_STATS_DAYS as usize])
+            .get_mut(day)
+        {
+            *count_on_day += count;
+        }
+    }
+    let mut versions: Vec<(Option<semver::Version>, VersionDownloads)> = series
+        .into_iter()
+        .map(|(num, downloads)| {
+            (
+                semver::Version::parse(&num).ok(),
+                VersionDownloads { num, downloads },
+            )
+        })
+        .collect();
+    versions.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+    Json(DownloadStats {
+        dates: (0..DOWNLOAD_STATS_DAYS)
+            .map(|day| {
+                (since + chrono::Duration::days(day))
+                    .format("%Y-%m-%d")
+                    .to_string()
+            })
+            .collect(),
+        versions: versions.into_iter().map(|(_, v)| v).collect(),
+    })
+    .into_response()
+}
+
+pub async fn readme(
+    State(storage): State<Storage>,
+    State(db_pool): State<DbPool>,
+    headers: HeaderMap,
+    Path((author, name, version)): Path<(String, String, String)>,
+) -> impl IntoResponse {
+    let mut conn = db_pool.read.get().await.unwrap();
+    let name = name.to_lowercase();
+    let (user, plugin) = match lookup_plugin(&mut conn, &author, &name).await {
+        Ok(found) => found,
+        Err(resp) => return resp,
+    };
+    let version = match lookup_version(&mut conn, &plugin, &version).await {
+        Ok(version) => version,
+        Err(resp) => return resp,
+    };
+    let etag = version_file_etag(&version, "readme");
+    if cache::not_modified(&headers, &etag) {
+        return cache::cached(&headers, &etag, cache::IMMUTABLE, ());
+    }
+
     let s3_path = format!("{}/{}/{}/readme", user.gh_login, name, version.num);
-    let result = bucket.get_object(&s3_path).await;
-    let resp = match result {
-        Ok(resp) => resp,
+    match storage.get(&s3_path).await {
+        Ok(Some(readme)) => cache::cached(&headers, &etag, cache::IMMUTABLE, readme),
+        Ok(None) => (StatusCode::NOT_FOUND, "can't download readme").into_response(),
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "can't download readme").into_response(),
+    }
+}
+
+/// The README rendered and sanitized at publish time. Versions published
+/// before that are rendered on the first request.
+pub async fn readme_html(
+    State(storage): State<Storage>,
+    State(db_pool): State<DbPool>,
+    headers: HeaderMap,
+    Path((author, name, version)): Path<(String, String, String)>,
+) -> impl IntoResponse {
+    let mut conn = db_pool.read.get().await.unwrap();
+    let name = name.to_lowercase();
+    let (user, plugin) = match lookup_plugin(&mut conn, &author, &name).await {
+        Ok(found) => found,
+        Err(resp) => return resp,
+    };
+    let version = match lookup_version(&mut conn, &plugin, &version).await {
+        Ok(version) => version,
+        Err(resp) => return resp,
+    };
+    let etag = version_file_etag(&version, "readme.html");
+    if cache::not_modified(&headers, &etag) {
+        return cache::cached(&headers, &etag, cache::IMMUTABLE, ());
+    }
+
+    let folder = format!("{}/{}/{}", user.gh_login, name, version.num);
+    let html = match storage.get(&format!("{folder}/readme.html")).await {
+        Ok(Some(html)) => html,
+        Ok(None) => match storage.get(&format!("{folder}/readme")).await {
+            Ok(Some(readme)) => {
+                let links = readme::Links::new(
+                    &user.gh_login,
+                    &name,
+                    &version.num,
+                    plugin.repository.as_deref(),
+                );
+                let html = readme::render(&String::from_utf8_lossy(&readme), &links).into_bytes();
+                let _ = storage
+                    .put(
+                        &format!("{folder}/readme.html"),
+                        &html,
+                        Some(README_HTML_CONTENT_TYPE),
+                    )
+                    .await;
+                html
+            }
+            Ok(None) => return (StatusCode::NOT_FOUND, "no readme").into_response(),
+            Err(_) => {
+                return (StatusCode::INTERNAL_SERVER_ERROR, "can't download readme").into_response()
+            }
+        },
         Err(_) => {
-            return (
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                "can't download readme",
-            )
-                .into_response();
+            return (StatusCode::INTERNAL_SERVER_ERROR, "can't download readme").into_response()
         }
     };
-    if resp.status_code() != 200 {
-        return (
-            axum::http::StatusCode::from_u16(resp.status_code()).unwrap(),
-            "can't download readme",
-        )
-            .into_response();
+
+    cache::cached(
+        &headers,
+        &etag,
+        cache::IMMUTABLE,
+        (
+            [(axum::http::header::CONTENT_TYPE, README_HTML_CONTENT_TYPE)],
+            html,
+        ),
+    )
+}
+
+/// Files from the archive shown in the README, like screenshots.
+pub async fn file(
+    State(storage): State<Storage>,
+    State(db_pool): State<DbPool>,
+    headers: HeaderMap,
+    Path((author, name, version, path)): Path<(String, String, String, String)>,
+) -> impl IntoResponse {
+    let path = match readme::normalize(&path).filter(|path| readme::is_file(path)) {
+        Some(path) => path,
+        None => return (StatusCode::NOT_FOUND, "file not found").into_response(),
+    };
+    let mut conn = db_pool.read.get().await.unwrap();
+    let name = name.to_lowercase();
+    let (user, plugin) = match lookup_plugin(&mut conn, &author, &name).await {
+        Ok(found) => found,
+        Err(resp) => return resp,
+    };
+    let version = match lookup_version(&mut conn, &plugin, &version).await {
+        Ok(version) => version,
+        Err(resp) => return resp,
+    };
+    let etag = version_file_etag(&version, &format!("files/{path}"));
+    if cache::not_modified(&headers, &etag) {
+        return cache::cached(&headers, &etag, cache::IMMUTABLE, ());
     }
 
-    resp.bytes().to_vec().into_response()
+    let s3_path = format!("{}/{}/{}/files/{path}", user.gh_login, name, version.num);
+    let content = match storage.get(&s3_path).await {
+        Ok(Some(content)) => content,
+        Ok(None) => return (StatusCode::NOT_FOUND, "file not found").into_response(),
+        Err(_) => {
+            return (StatusCode::INTERNAL_SERVER_ERROR, "can't download file").into_response()
+        }
+    };
+    cache::cached(
+        &headers,
+        &etag,
+        cache::IMMUTABLE,
+        (
+            [
+                (
+                    axum::http::header::CONTENT_TYPE,
+                    readme::file_content_type(&path),
+                ),
+                // SVGs are opened on the registry's origin, so they can't run
+                // scripts
+                (
+                    axum::http::header::CONTENT_SECURITY_POLICY,
+                    "default-src 'none'; style-src 'unsafe-inline'; sandbox",
+                ),
+                (axum::http::header::X_CONTENT_TYPE_OPTIONS, "nosniff"),
+            ],
+            content,
+        ),
+    )
 }
 
 pub async fn icon(
-    State(bucket): State<Bucket>,
+    State(storage): State<Storage>,
     State(db_pool): State<DbPool>,
+    headers: HeaderMap,
     Path((author, name, version)): Path<(String, String, String)>,
 ) -> axum::response::Response {
     let mut conn = db_pool.read.get().await.unwrap();
-    let user = find_user_by_gh_login(&mut conn, &author).await.unwrap();
     let name = name.to_lowercase();
-    let plugin = find_plugin(&mut conn, &user, &name).await.unwrap();
-    let version = find_plugin_version(&mut conn, &plugin, &version)
-        .await
-        .unwrap();
+    let (user, plugin) = match lookup_plugin(&mut conn, &author, &name).await {
+        Ok(found) => found,
+        Err(resp) => return resp,
+    };
+    let version = match lookup_version(&mut conn, &plugin, &version).await {
+        Ok(version) => version,
+        Err(resp) => return resp,
+    };
+    let etag = version_file_etag(&version, "icon");
+    if cache::not_modified(&headers, &etag) {
+        return cache::cached(&headers, &etag, cache::IMMUTABLE, ());
+    }
+
     let s3_path = format!("{}/{}/{}/icon", user.gh_login, name, version.num);
-    let content_type = match bucket.head_object(&s3_path).await {
-        Ok((head, _)) => head.content_type,
+    let content_type = match storage.head(&s3_path).await {
+        Ok(Some(info)) => info.content_type,
+        Ok(None) => return (StatusCode::NOT_FOUND, "can't download icon").into_response(),
         Err(_) => {
-            return (
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                "can't download icon",
-            )
-                .into_response();
+            return (StatusCode::INTERNAL_SERVER_ERROR, "can't download icon").into_response();
         }
     };
 
-    let result = bucket.get_object(&s3_path).await;
-    let resp = match result {
-        Ok(resp) => resp,
+    let icon = match storage.get(&s3_path).await {
+        Ok(Some(icon)) => icon,
+        Ok(None) => return (StatusCode::NOT_FOUND, "can't download icon").into_response(),
         Err(_) => {
-            return (
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                "can't download icon",
-            )
-                .into_response();
+            return (StatusCode::INTERNAL_SERVER_ERROR, "can't download icon").into_response();
         }
     };
-    if resp.status_code() != 200 {
-        return (
-            axum::http::StatusCode::from_u16(resp.status_code()).unwrap(),
-            "can't download icon",
-        )
-            .into_response();
-    }
 
-    let mut res = axum::body::Full::from(resp.bytes().to_vec()).into_response();
+    let mut res = axum::body::Full::from(icon).into_response();
     res.headers_mut().insert(
         axum::http::header::CONTENT_TYPE,
         axum::http::header::HeaderValue::from_str(
@@ -351,18 +731,15 @@ pub async fn icon(
         )
         .unwrap(),
     );
-    res.headers_mut().insert(
-        axum::http::header::CACHE_CONTROL,
-        axum::http::header::HeaderValue::from_static("public, max-age=86400"),
-    );
-    res
+    cache::cached(&headers, &etag, cache::IMMUTABLE, res)
 }
 
 pub async fn publish(
     State(db_pool): State<DbPool>,
-    State(bucket): State<Bucket>,
+    State(storage): State<Storage>,
+    State(github_client): State<GithubClient>,
     TypedHeader(token): TypedHeader<headers::Authorization<Bearer>>,
-    body: BodyStream,
+    request: Request<Body>,
 ) -> impl IntoResponse {
     let api_token = {
         let mut conn = db_pool.write.get().await.unwrap();
@@ -373,28 +750,86 @@ pub async fn publish(
             }
         }
     };
+    if !api_token.has_scope(TOKEN_SCOPE_PUBLISH) {
+        return (
+            axum::http::StatusCode::FORBIDDEN,
+            "API Token doesn't have the publish scope",
+        )
+            .into_response();
+    }
 
     let user = {
         let mut conn = db_pool.read.get().await.unwrap();
         find_user(&mut conn, api_token.user_id).await.unwrap()
     };
 
+    let upload_dir = tempfile::TempDir::new().unwrap();
+    let archive = upload_dir.path().join(VOLT_ARCHIVE);
+    let is_multipart = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .map(|content_type| content_type.starts_with("multipart/form-data"))
+        .unwrap_or(false);
+    // Release notes come in a form with the archive, older clients send just
+    // the archive
+    let notes = if is_multipart {
+        match read_publish_form(request, &archive).await {
+            Ok(notes) => notes,
+            Err(resp) => return resp,
+        }
+    } else {
+        let body = BodyStream::from_request(request, &()).await.unwrap();
+        stream_to_file(&archive, body).await.unwrap();
+        None
+    };
+    let notes = match release_notes(notes) {
+        Ok(notes) => notes,
+        Err(resp) => return resp,
+    };
+
+    publish_archive(
+        &db_pool,
+        &storage,
+        &github_client,
+        &user,
+        api_token.plugin_id,
+        &archive,
+        notes,
+    )
+    .await
+}
+
+/// Validates an uploaded plugin archive and publishes it as a new version of
+/// the user's plugin. Tokens limited to one plugin give its id as
+/// `only_plugin`. Without `notes`, the release notes are taken from
+/// `CHANGELOG.md`.
+pub(crate) async fn publish_archive(
+    db_pool: &DbPool,
+    storage: &Storage,
+    github_client: &GithubClient,
+    user: &User,
+    only_plugin: Option<i32>,
+    archive: &std::path::Path,
+    notes: Option<String>,
+) -> Response {
     let dir = tempfile::TempDir::new().unwrap();
     let dest = tempfile::TempDir::new().unwrap();
-    let archive = dir.path().join(VOLT_ARCHIVE);
-    stream_to_file(&archive, body).await.unwrap();
 
     {
-        let archive = archive.clone();
+        let archive = archive.to_path_buf();
         let dir_path = dir.path().to_path_buf();
-        tokio::task::spawn_blocking(move || {
-            let archive = File::open(archive).unwrap();
-            let tar = Decoder::new(archive).unwrap();
+        let unpacked = tokio::task::spawn_blocking(move || {
+            let archive = File::open(archive)?;
+            let tar = Decoder::new(archive)?;
             let mut archive = Archive::new(tar);
-            archive.unpack(dir_path).unwrap();
+            archive.unpack(dir_path)
         })
         .await
         .unwrap();
+        if unpacked.is_err() {
+            return (StatusCode::BAD_REQUEST, "plugin archive invalid").into_response();
+        }
     }
 
     let volt_path = dir.path().join(VOLT_MANIFEST);
@@ -426,6 +861,57 @@ pub async fn publish(
         return (StatusCode::BAD_REQUEST, "version isn't valid").into_response();
     }
 
+    {
+        let mut conn = db_pool.read.get().await.unwrap();
+        // The name is only kept from whoever takes over the login, the owner
+        // can publish it again
+        let reservation = find_name_reservation(&mut conn, &user.gh_login, &volt.name)
+            .await
+            .unwrap()
+            .filter(|reservation| reservation.user_id != user.id);
+        if let Some(reservation) = reservation {
+            return (
+                StatusCode::CONFLICT,
+                format!(
+                    "{} was deleted and its name is reserved until {}",
+                    volt.name,
+                    reservation.reserved_until.format("%Y-%m-%d %H:%M:%S")
+                ),
+            )
+                .into_response();
+        }
+    }
+
+    {
+        let mut conn = db_pool.read.get().await.unwrap();
+        // Versions are served as immutable, so once published they stay as
+        // they are
+        let published = match find_plugin(&mut conn, user, &volt.name).await {
+            Ok(plugin) => find_plugin_version(&mut conn, &plugin, &volt.version)
+                .await
+                .is_ok(),
+            Err(_) => false,
+        };
+        if published {
+            return version_published(&volt.name, &volt.version);
+        }
+    }
+
+    if let Some(plugin_id) = only_plugin {
+        let mut conn = db_pool.read.get().await.unwrap();
+        let allowed = find_plugin(&mut conn, user, &volt.name)
+            .await
+            .map(|plugin| plugin.id == plugin_id)
+            .unwrap_or(false);
+        if !allowed {
+            return (
+                StatusCode::FORBIDDEN,
+                format!("API Token can't publish {}", volt.name),
+            )
+                .into_response();
+        }
+    }
+
     {
         let dest_volt_path = dest.path().join(VOLT_MANIFEST);
         tokio::fs::write(
@@ -520,10 +1006,11 @@ pub async fn publish(
                 }
 
                 let icon_content = tokio::fs::read(&icon_path).await.unwrap();
-                bucket
-                    .put_object(
+                storage
+                    .put(
                         &format!("{}/{}/{}/icon", user.gh_login, volt.name, volt.version),
                         &icon_content,
+                        None,
                     )
                     .await
                     .unwrap();
@@ -539,13 +1026,73 @@ pub async fn publish(
         return (StatusCode::BAD_REQUEST, "not a valid plugin").into_response();
     }
 
+    let notes = match notes {
+        Some(notes) => Some(notes),
+        None => {
+            let changelog_path = dir.path().join(CHANGELOG);
+            // Not following symlinks, which could point anywhere on the server
+            match tokio::fs::symlink_metadata(&changelog_path).await {
+                Ok(meta) if meta.is_file() => {
+                    let changelog = tokio::fs::read(&changelog_path).await.unwrap();
+                    changelog::release_notes(&String::from_utf8_lossy(&changelog), &volt.version)
+                }
+                _ => None,
+            }
+        }
+    };
+
     let readme_path = dir.path().join("README.md");
     if readme_path.exists() {
         let readme = tokio::fs::read(&readme_path).await.unwrap();
-        bucket
-            .put_object(
+        storage
+            .put(
                 &format!("{}/{}/{}/readme", user.gh_login, volt.name, volt.version),
                 &readme,
+                None,
+            )
+            .await
+            .unwrap();
+        let readme = String::from_utf8_lossy(&readme);
+        let mut links = readme::Links::new(
+            &user.gh_login,
+            &volt.name,
+            &volt.version,
+            volt.repository.as_deref(),
+        );
+        let archive_root = tokio::fs::canonicalize(dir.path()).await.unwrap();
+        for path in readme::linked_files(&readme)
+            .into_iter()
+            .take(MAX_README_FILES)
+        {
+            // The archive can have symlinks pointing anywhere on the server
+            let file_path = match tokio::fs::canonicalize(dir.path().join(&path)).await {
+                Ok(file_path) if file_path.starts_with(&archive_root) => file_path,
+                _ => continue,
+            };
+            match tokio::fs::metadata(&file_path).await {
+                Ok(meta) if meta.is_file() && meta.len() <= MAX_README_FILE_SIZE => {}
+                _ => continue,
+            }
+            let content = tokio::fs::read(&file_path).await.unwrap();
+            storage
+                .put(
+                    &format!("{s3_folder}/files/{path}"),
+                    &content,
+                    Some(readme::file_content_type(&path)),
+                )
+                .await
+                .unwrap();
+            links.files.insert(path);
+        }
+        let readme_html = readme::render(&readme, &links);
+        storage
+            .put(
+                &format!(
+                    "{}/{}/{}/readme.html",
+                    user.gh_login, volt.name, volt.version
+                ),
+                readme_html.as_bytes(),
+                Some(README_HTML_CONTENT_TYPE),
             )
             .await
             .unwrap();
@@ -564,11 +1111,11 @@ pub async fn publish(
                 _ => "image/*",
             };
             let icon_content = tokio::fs::read(&icon_path).await.unwrap();
-            bucket
-                .put_object_with_content_type(
+            storage
+                .put(
                     &format!("{}/{}/{}/icon", user.gh_login, volt.name, volt.version),
                     &icon_content,
-                    content_type,
+                    Some(content_type),
                 )
                 .await
                 .unwrap();
@@ -595,12 +1142,23 @@ pub async fn publish(
         .unwrap();
     }
 
+    // The repository is shown on the plugin page, so it's only marked as the
+    // plugin's own when the publisher could push to it.
+    let repository_verified = match volt.repository.as_deref().and_then(parse_repository_url) {
+        Some((owner, repo)) => {
+            let auth = AccessToken::new(user.gh_access_token.clone());
+            github_client.can_publish_from(owner, repo, &auth).await
+        }
+        None => false,
+    };
+
     let volt_content = tokio::fs::read(&dest_volt_archive).await.unwrap();
-    bucket
-        .put_object_with_content_type(
-            format!("{s3_folder}/{VOLT_ARCHIVE}"),
+    let checksum = format!("{:x}", Sha256::digest(&volt_content));
+    storage
+        .put(
+            &format!("{s3_folder}/{VOLT_ARCHIVE}"),
             &volt_content,
-            "application/zstd",
+            Some("application/zstd"),
         )
         .await
         .unwrap();
@@ -617,21 +1175,41 @@ pub async fn publish(
                     &volt.display_name,
                     &volt.description,
                     volt.repository.as_deref(),
+                    repository_verified,
                     is_wasm,
                 );
                 let plugin = new_plugin.create_or_update(conn).await?;
-                let new_version = NewVersion::new(plugin.id, &volt.version);
-                new_version.create_or_update(conn).await?;
+                let new_version =
+                    NewVersion::new(plugin.id, &volt.version, &checksum, notes.as_deref());
+                new_version.create(conn).await?;
                 Ok(())
             }
             .boxed()
         })
         .await;
-    result.unwrap();
+    if let Err(e) = result {
+        // Another publish of the same version got there first
+        if let Some(diesel::result::Error::DatabaseError(
+            diesel::result::DatabaseErrorKind::UniqueViolation,
+            _,
+        )) = e.downcast_ref::<diesel::result::Error>()
+        {
+            return version_published(&volt.name, &volt.version);
+        }
+        panic!("{e}");
+    }
 
     ().into_response()
 }
 
+fn version_published(name: &str, version: &str) -> Response {
+    (
+        StatusCode::CONFLICT,
+        format!("{name} v{version} is already published, publish a new version instead"),
+    )
+        .into_response()
+}
+
 async fn stream_to_file<S, E>(path: &std::path::Path, stream: S) -> Result<()>
 where
     S: Stream<Item = Result<Bytes, E>>,
@@ -647,11 +1225,97 @@ where
     Ok(())
 }
 
+/// Writes the `archive` field of a publish form to `archive`, returning the
+/// `notes` field.
+async fn read_publish_form(
+    request: Request<Body>,
+    archive: &std::path::Path,
+) -> Result<Option<String>, Response> {
+    let mut multipart = Multipart::from_request(request, &())
+        .await
+        .map_err(|e| e.into_response())?;
+    let mut notes = None;
+    let mut has_archive = false;
+    loop {
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(_) => return Err((StatusCode::BAD_REQUEST, "publish form invalid").into_response()),
+        };
+        let name = field.name().map(|name| name.to_string());
+        match name.as_deref() {
+            Some("notes") => match field.text().await {
+                Ok(text) => notes = Some(text),
+                Err(_) => {
+                    return Err((StatusCode::BAD_REQUEST, "release notes invalid").into_response())
+                }
+            },
+            Some("archive") => {
+                if stream_to_file(archive, field).await.is_err() {
+                    return Err((StatusCode::BAD_REQUEST, "plugin archive invalid").into_response());
+                }
+                has_archive = true;
+            }
+            _ => {}
+        }
+    }
+    if !has_archive {
+        return Err((StatusCode::BAD_REQUEST, "no plugin archive").into_response());
+    }
+    Ok(notes)
+}
+
+/// Checks the release notes given when publishing.
+pub(crate) fn release_notes(notes: Option<String>) -> Result<Option<String>, Response> {
+    let notes = notes
+        .map(|notes| notes.trim().to_string())
+        .filter(|notes| !notes.is_empty());
+    if let Some(notes) = notes.as_ref() {
+        if notes.chars().count() > changelog::MAX_RELEASE_NOTES_LEN {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!(
+                    "release notes can't be longer than {} characters",
+                    changelog::MAX_RELEASE_NOTES_LEN
+                ),
+            )
+                .into_response());
+        }
+    }
+    Ok(notes)
+}
+
+/// Longest reason a version can be yanked with
+const MAX_YANK_REASON_LEN: usize = 200;
+
+pub(crate) fn yank_reason(payload: Option<Json<YankPayload>>) -> Result<Option<String>, Response> {
+    let reason = payload
+        .and_then(|Json(payload)| payload.reason)
+        .map(|reason| reason.trim().to_string())
+        .filter(|reason| !reason.is_empty());
+    if let Some(reason) = reason.as_ref() {
+        if reason.chars().count() > MAX_YANK_REASON_LEN {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("yank reason can't be longer than {MAX_YANK_REASON_LEN} characters"),
+            )
+                .into_response());
+        }
+    }
+    Ok(reason)
+}
+
 pub async fn yank(
     TypedHeader(token): TypedHeader<headers::Authorization<Bearer>>,
     State(db_pool): State<DbPool>,
     Path((name, version)): Path<(String, String)>,
+    payload: Option<Json<YankPayload>>,
 ) -> impl IntoResponse {
+    let reason = match yank_reason(payload) {
+        Ok(reason) => reason,
+        Err(resp) => return resp,
+    };
+
     let api_token = {
         let mut conn = db_pool.write.get().await.unwrap();
         match find_api_token(&mut conn, token.token()).await {
@@ -661,13 +1325,20 @@ pub async fn yank(
             }
         }
     };
+    if !api_token.has_scope(TOKEN_SCOPE_YANK) {
+        return (
+            axum::http::StatusCode::FORBIDDEN,
+            "API Token doesn't have the yank scope",
+        )
+            .into_response();
+    }
 
     let user = {
         let mut conn = db_pool.read.get().await.unwrap();
         find_user(&mut conn, api_token.user_id).await.unwrap()
     };
 
-    if let Err(e) = modify_yank(&user, State(db_pool), Path((name, version)), true).await {
+    if let Err(e) = modify_yank(&user, &db_pool, &name, &version, true, reason.as_deref()).await {
         return (axum::http::StatusCode::BAD_REQUEST, e.to_string()).into_response();
     }
 
@@ -688,34 +1359,134 @@ pub async fn unyank(
             }
         }
     };
+    if !api_token.has_scope(TOKEN_SCOPE_YANK) {
+        return (
+            axum::http::StatusCode::FORBIDDEN,
+            "API Token doesn't have the yank scope",
+        )
+            .into_response();
+    }
 
     let user = {
         let mut conn = db_pool.read.get().await.unwrap();
         find_user(&mut conn, api_token.user_id).await.unwrap()
     };
 
-    if let Err(e) = modify_yank(&user, State(db_pool), Path((name, version)), false).await {
+    if let Err(e) = modify_yank(&user, &db_pool, &name, &version, false, None).await {
         return (axum::http::StatusCode::BAD_REQUEST, e.to_string()).into_response();
     }
 
     ().into_response()
 }
 
-async fn modify_yank(
+pub(crate) async fn modify_yank(
     user: &User,
-    State(db_pool): State<DbPool>,
-    Path((name, version)): Path<(String, String)>,
+    db_pool: &DbPool,
+    name: &str,
+    version: &str,
     yanked: bool,
+    reason: Option<&str>,
 ) -> Result<()> {
     let plugin = {
         let mut conn = db_pool.read.get().await?;
-        find_plugin(&mut conn, user, &name).await?
+        find_plugin(&mut conn, user, &name.to_lowercase()).await?
     };
 
     {
         let mut conn = db_pool.write.get().await?;
-        modify_plugin_version_yank(&mut conn, &plugin, &version, yanked).await?;
+        modify_plugin_version_yank(&mut conn, &plugin, version, yanked, reason).await?;
     }
 
     Ok(())
 }
+
+/// How long after being first published owners can delete a plugin
+const DEFAULT_DELETE_GRACE_HOURS: i64 = 72;
+/// How long the name of a deleted plugin stays reserved for its owner
+const DEFAULT_NAME_RESERVATION_DAYS: i64 = 30;
+
+fn delete_grace_period() -> chrono::Duration {
+    let hours = std::env::var("PLUGIN_DELETE_GRACE_HOURS")
+        .ok()
+        .and_then(|hours| hours.parse().ok())
+        .unwrap_or(DEFAULT_DELETE_GRACE_HOURS);
+    chrono::Duration::hours(hours)
+}
+
+fn name_reservation_period() -> chrono::Duration {
+    let days = std::env::var("PLUGIN_NAME_RESERVATION_DAYS")
+        .ok()
+        .and_then(|days| days.parse().ok())
+        .unwrap_or(DEFAULT_NAME_RESERVATION_DAYS);
+    chrono::Duration::days(days)
+}
+
+/// Deletes a plugin with all its versions. Owners can do so within the grace
+/// period after first publishing it, admins at any time.
+pub async fn delete(
+    TypedHeader(token): TypedHeader<headers::Authorization<Bearer>>,
+    State(db_pool): State<DbPool>,
+    State(storage): State<Storage>,
+    Path((author, name)): Path<(String, String)>,
+) -> impl IntoResponse {
+    let mut conn = db_pool.write.get().await.unwrap();
+    let api_token = match find_api_token(&mut conn, token.token()).await {
+        Ok(api_token) => api_token,
+        Err(_) => return (StatusCode::UNAUTHORIZED, "API Token Invalid").into_response(),
+    };
+    if !api_token.has_scope(TOKEN_SCOPE_DELETE) {
+        return (
+            StatusCode::FORBIDDEN,
+            "API Token doesn't have the delete scope",
+        )
+            .into_response();
+    }
+    let user = find_user(&mut conn, api_token.user_id).await.unwrap();
+    let admin = is_admin(&user);
+
+    let owner = match find_user_by_gh_login(&mut conn, &author).await {
+        Ok(owner) => owner,
+        Err(_) => return (StatusCode::NOT_FOUND, "plugin not found").into_response(),
+    };
+    if owner.id != user.id && !admin {
+        return (
+            StatusCode::FORBIDDEN,
+            "only the plugin's owner or an admin can delete it",
+        )
+            .into_response();
+    }
+    let plugin = match find_plugin(&mut conn, &owner, &name.to_lowercase()).await {
+        Ok(plugin) => plugin,
+        Err(_) => return (StatusCode::NOT_FOUND, "plugin not found").into_response(),
+    };
+
+    let now = chrono::Utc::now().naive_utc();
+    let grace_period = delete_grace_period();
+    if !admin && plugin.created_at + grace_period < now {
+        return (
+            StatusCode::FORBIDDEN,
+            format!(
+                "plugins can only be deleted within {} hours of being first published, ask an admin to delete it",
+                grace_period.num_hours()
+            ),
+        )
+            .into_response();
+    }
+
+    let reserved_until = now + name_reservation_period();
+    delete_plugin(&mut conn, &plugin, &owner, &user, reserved_until)
+        .await
+        .unwrap();
+
+    // Nothing refers to the files anymore, so ones left behind by a failure
+    // here are only wasted space, and publishing the name again overwrites them.
+    let prefix = format!("{}/{}/", owner.gh_login, plugin.name);
+    storage.delete_prefix(&prefix).await;
+
+    Json(PluginDeleted {
+        author: owner.gh_login,
+        name: plugin.name,
+        reserved_until,
+    })
+    .into_response()
+}
@@ -0,0 +1,365 @@
+//! READMEs are rendered to HTML when a version is published, since the HTML is
+//! served from the registry's own origin and has to be sanitized first. Fenced
+//! code blocks are highlighted with `hl-` prefixed classes, which the frontend
+//! styles in `highlight.css`.
+
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+    sync::{Arc, Mutex},
+};
+
+use ammonia::{UrlRelative, UrlRelativeEvaluate};
+use once_cell::sync::Lazy;
+use pulldown_cmark::{html, CodeBlockKind, Event, Options, Parser, Tag};
+use syntect::{
+    html::{ClassStyle, ClassedHTMLGenerator},
+    parsing::SyntaxSet,
+    util::LinesWithEndings,
+};
+
+use crate::github::parse_repository_url;
+
+/// Files a README can show from the archive
+const FILE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "svg", "webp"];
+
+const CLASS_PREFIX: &str = "hl-";
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed {
+    prefix: CLASS_PREFIX,
+};
+
+static SYNTAX_SET: Lazy<SyntaxSet> = Lazy::new(SyntaxSet::load_defaults_newlines);
+
+/// Where relative links in a README point to.
+#[derive(Clone, Default)]
+pub(crate) struct Links {
+    /// URL the files of the version are served from, ending with `/`
+    pub files_url: String,
+    /// Files from the archive served from `files_url`
+    pub files: HashSet<String>,
+    /// The GitHub repository, as `https://github.com/{owner}/{repo}`
+    pub repository: Option<String>,
+    /// Tag of the version in the repository
+    pub tag: String,
+}
+
+impl Links {
+    /// Other relative links go into the plugin's GitHub repository, at the
+    /// `v{version}` tag.
+    pub fn new(author: &str, name: &str, version: &str, repository: Option<&str>) -> Self {
+        Self {
+            files_url: format!("/api/v1/plugins/{author}/{name}/{version}/files/"),
+            files: HashSet::new(),
+            repository: repository
+                .and_then(parse_repository_url)
+                .map(|(owner, repo)| format!("https://github.com/{owner}/{repo}")),
+            tag: format!("v{version}"),
+        }
+    }
+}
+
+impl UrlRelativeEvaluate for Links {
+    fn evaluate<'a>(&self, url: &'a str) -> Option<Cow<'a, str>> {
+        if url.starts_with('#') {
+            return Some(Cow::Borrowed(url));
+        }
+        let (path, suffix) = match url.find(['?', '#']) {
+            Some(i) => url.split_at(i),
+            None => (url, ""),
+        };
+        let path = normalize(path)?;
+        if self.files.contains(&path) {
+            return Some(Cow::Owned(format!("{}{path}{suffix}", self.files_url)));
+        }
+        let repository = self.repository.as_ref()?;
+        // GitHub shows files under `blob`, and serves them under `raw`
+        let kind = if is_file(&path) { "raw" } else { "blob" };
+        Some(Cow::Owned(format!(
+            "{repository}/{kind}/{}/{path}{suffix}",
+            self.tag
+        )))
+    }
+}
+
+/// The path from the root of the archive, or `None` if it leads out of it.
+pub(crate) fn normalize(path: &str) -> Option<String> {
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop()?;
+            }
+            segment => segments.push(segment),
+        }
+    }
+    if segments.is_empty() {
+        return None;
+    }
+    Some(segments.join("/"))
+}
+
+fn extension(path: &str) -> Option<String> {
+    path.rsplit_once('.').map(|(_, ext)| ext.to_lowercase())
+}
+
+/// Whether the path has one of the extensions served from the archive.
+pub(crate) fn is_file(path: &str) -> bool {
+    extension(path)
+        .map(|ext| FILE_EXTENSIONS.contains(&ext.as_str()))
+        .unwrap_or(false)
+}
+
+pub(crate) fn file_content_type(path: &str) -> &'static str {
+    match extension(path).as_deref() {
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        Some("webp") => "image/webp",
+        _ => "application/octet-stream",
+    }
+}
+
+/// The code block as HTML, or `None` if the language isn't known.
+fn highlight(lang: &str, code: &str) -> Option<String> {
+    // Info strings can have more after the language, like `rust,ignore`
+    let lang = lang
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .next()
+        .unwrap_or("");
+    if lang.is_empty() {
+        return None;
+    }
+    let syntax = SYNTAX_SET.find_syntax_by_token(lang)?;
+    let mut generator =
+        ClassedHTMLGenerator::new_with_class_style(syntax, &SYNTAX_SET, CLASS_STYLE);
+    for line in LinesWithEndings::from(code) {
+        generator
+            .parse_html_for_line_which_includes_newline(line)
+            .ok()?;
+    }
+    Some(format!("<pre><code>{}</code></pre>", generator.finalize()))
+}
+
+/// Replaces fenced code blocks in a known language with highlighted HTML.
+fn highlight_code_blocks<'a>(parser: Parser<'a, 'a>) -> Vec<Event<'a>> {
+    let mut events = Vec::new();
+    let mut code_block: Option<(String, Vec<Event<'a>>)> = None;
+    for event in parser {
+        match event {
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(lang))) if code_block.is_none() => {
+                code_block = Some((
+                    lang.to_string(),
+                    vec![Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(lang)))],
+                ));
+            }
+            Event::End(Tag::CodeBlock(kind)) if code_block.is_some() => {
+                let (lang, mut block) = code_block.take().unwrap();
+                let code: String = block
+                    .iter()
+                    .filter_map(|event| match event {
+                        Event::Text(text) => Some(text.as_ref()),
+                        _ => None,
+                    })
+                    .collect();
+                match highlight(&lang, &code) {
+                    Some(html) => events.push(Event::Html(html.into())),
+                    None => {
+                        block.push(Event::End(Tag::CodeBlock(kind)));
+                        events.append(&mut block);
+                    }
+                }
+            }
+            event => match code_block.as_mut() {
+                Some((_, block)) => block.push(event),
+                None => events.push(event),
+            },
+        }
+    }
+    events
+}
+
+/// Keeps the `class` attribute only for the classes of highlighted code.
+fn filter_attribute<'a>(_element: &str, attribute: &str, value: &'a str) -> Option<Cow<'a, str>> {
+    if attribute != "class" {
+        return Some(Cow::Borrowed(value));
+    }
+    if value
+        .split_whitespace()
+        .all(|class| class.starts_with(CLASS_PREFIX))
+    {
+        Some(Cow::Borrowed(value))
+    } else {
+        None
+    }
+}
+
+fn clean(markdown: &str, url_relative: UrlRelative) -> String {
+    let parser = Parser::new_ext(markdown, Options::all());
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, highlight_code_blocks(parser).into_iter());
+
+    // The default allow-list drops scripts, styles, event handlers and
+    // `javascript:` URLs, and adds `rel="noopener noreferrer"` to links. Task
+    // lists are kept as disabled checkboxes. Classes are only kept on the
+    // spans of highlighted code, so READMEs can't restyle the page.
+    ammonia::Builder::default()
+        .add_tags(&["input"])
+        .add_tag_attributes("span", &["class"])
+        .attribute_filter(filter_attribute)
+        .add_tag_attributes("input", &["checked"])
+        .set_tag_attribute_value("input", "type", "checkbox")
+        .set_tag_attribute_value("input", "disabled", "")
+        .url_relative(url_relative)
+        .clean(&unsafe_html)
+        .to_string()
+}
+
+/// Collects the files a README links to, leaving the links out.
+struct LinkedFiles {
+    paths: Arc<Mutex<Vec<String>>>,
+}
+
+impl UrlRelativeEvaluate for LinkedFiles {
+    fn evaluate<'a>(&self, url: &'a str) -> Option<Cow<'a, str>> {
+        let path = url.split(['?', '#']).next().unwrap_or("");
+        if let Some(path) = normalize(path).filter(|path| is_file(path)) {
+            self.paths.lock().unwrap().push(path);
+        }
+        None
+    }
+}
+
+/// Paths of the files in the archive the README links to, which are published
+/// with it.
+pub(crate) fn linked_files(markdown: &str) -> Vec<String> {
+    let paths = Arc::new(Mutex::new(Vec::new()));
+    clean(
+        markdown,
+        UrlRelative::Custom(Box::new(LinkedFiles {
+            paths: paths.clone(),
+        })),
+    );
+    let mut paths = std::mem::take(&mut *paths.lock().unwrap());
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
+pub(crate) fn render(markdown: &str, links: &Links) -> String {
+    clean(markdown, UrlRelative::Custom(Box::new(links.clone())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn links() -> Links {
+        let mut links = Links::new(
+            "alice",
+            "theme",
+            "1.0.0",
+            Some("https://github.com/alice/theme.git"),
+        );
+        links.files.insert("img/screenshot.png".to_string());
+        links
+    }
+
+    fn evaluate(links: &Links, url: &str) -> Option<String> {
+        links.evaluate(url).map(|url| url.into_owned())
+    }
+
+    #[test]
+    fn normalizes_paths_within_archive() {
+        assert_eq!(normalize("img/a.png").as_deref(), Some("img/a.png"));
+        assert_eq!(normalize("./img/a.png").as_deref(), Some("img/a.png"));
+        assert_eq!(normalize("/img//a.png").as_deref(), Some("img/a.png"));
+        assert_eq!(normalize("docs/../img/a.png").as_deref(), Some("img/a.png"));
+        assert_eq!(normalize("../a.png"), None);
+        assert_eq!(normalize("img/../../a.png"), None);
+        assert_eq!(normalize("img/.."), None);
+        assert_eq!(normalize(""), None);
+    }
+
+    #[test]
+    fn keeps_anchors() {
+        assert_eq!(evaluate(&links(), "#usage").as_deref(), Some("#usage"));
+    }
+
+    #[test]
+    fn points_archive_files_at_registry() {
+        let links = links();
+        assert_eq!(
+            evaluate(&links, "./img/screenshot.png").as_deref(),
+            Some("/api/v1/plugins/alice/theme/1.0.0/files/img/screenshot.png")
+        );
+        assert_eq!(
+            evaluate(&links, "docs/../img/screenshot.png?raw=true#top").as_deref(),
+            Some("/api/v1/plugins/alice/theme/1.0.0/files/img/screenshot.png?raw=true#top")
+        );
+    }
+
+    #[test]
+    fn points_other_paths_at_repository_tag() {
+        let links = links();
+        // Images not in the archive are served by GitHub, other files shown
+        assert_eq!(
+            evaluate(&links, "img/other.PNG").as_deref(),
+            Some("https://github.com/alice/theme/raw/v1.0.0/img/other.PNG")
+        );
+        assert_eq!(
+            evaluate(&links, "docs/usage.md#install").as_deref(),
+            Some("https://github.com/alice/theme/blob/v1.0.0/docs/usage.md#install")
+        );
+    }
+
+    #[test]
+    fn drops_paths_out_of_archive() {
+        let links = links();
+        assert_eq!(evaluate(&links, "../secret.png"), None);
+        assert_eq!(evaluate(&links, "img/../../secret.md"), None);
+    }
+
+    #[test]
+    fn drops_relative_links_without_repository() {
+        let mut links = Links::new("alice", "theme", "1.0.0", None);
+        links.files.insert("img/screenshot.png".to_string());
+        assert_eq!(
+            evaluate(&links, "img/screenshot.png").as_deref(),
+            Some("/api/v1/plugins/alice/theme/1.0.0/files/img/screenshot.png")
+        );
+        assert_eq!(evaluate(&links, "docs/usage.md"), None);
+    }
+
+    #[test]
+    fn renders_images_and_links() {
+        let html = render(
+            "![shot](img/screenshot.png) [usage](docs/usage.md) [site](https://example.com/a.png) [up](#top)",
+            &links(),
+        );
+        assert!(html.contains(
+            r#"<img src="/api/v1/plugins/alice/theme/1.0.0/files/img/screenshot.png" alt="shot">"#
+        ));
+        assert!(html.contains(r#"href="https://github.com/alice/theme/blob/v1.0.0/docs/usage.md""#));
+        // Absolute URLs aren't rewritten
+        assert!(html.contains(r#"href="https://example.com/a.png""#));
+        assert!(html.contains(r##"href="#top""##));
+    }
+
+    #[test]
+    fn renders_links_out_of_archive_without_target() {
+        let html = render("![x](../x.png) [y](../../y.md)", &links());
+        assert!(!html.contains("x.png"));
+        assert!(!html.contains("y.md"));
+    }
+
+    #[test]
+    fn collects_linked_files() {
+        let files = linked_files(
+            "![a](img/a.png) ![b](./img/a.png?x=1) ![c](docs/../c.SVG) [d](docs/d.md) \
+             ![e](../e.png) ![f](https://example.com/f.png)",
+        );
+        assert_eq!(files, vec!["c.SVG".to_string(), "img/a.png".to_string()]);
+    }
+}
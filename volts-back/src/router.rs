@@ -6,6 +6,7 @@ use axum::{
     routing::{delete, get, post, put},
     Json, Router, TypedHeader,
 };
+use headers::authorization::Bearer;
 use oauth2::{
     basic::BasicClient, reqwest::async_http_client, AuthorizationCode, Scope, TokenResponse,
 };
@@ -13,38 +14,71 @@ use serde::Deserialize;
 use volts_core::{db::models::User, MeUser, NewSessionResponse};
 
 use crate::{
-    db::{find_user, DbPool, NewUser},
+    db::{find_api_token, find_user, DbPool, NewUser},
     github::GithubClient,
-    plugin,
+    manage, plugin,
     state::{AppState, SESSION_COOKIE_NAME},
-    token,
+    token, trusted, upload, user,
 };
 
-pub fn build_router() -> Router<AppState> {
-    let state = AppState::new();
-
+pub fn build_router(state: AppState) -> Router<AppState> {
     let private_routes = Router::with_state(state.clone())
         .route("/session", get(new_session))
         .route("/session", delete(logout))
-        .route("/session/authorize", get(session_authorize));
+        .route("/session/authorize", get(session_authorize))
+        .route("/plugins", get(manage::plugins))
+        .route("/plugins/:author/:name/deprecate", put(manage::deprecate))
+        .route(
+            "/plugins/:author/:name/undeprecate",
+            put(manage::undeprecate),
+        )
+        .route("/plugins/:author/:name/owners", put(manage::add_owner))
+        .route(
+            "/plugins/:author/:name/owners/:login",
+            delete(manage::remove_owner),
+        )
+        .route("/plugins/:author/:name/:version/yank", put(manage::yank))
+        .route(
+            "/plugins/:author/:name/:version/unyank",
+            put(manage::unyank),
+        );
 
     let user_routes = Router::with_state(state.clone())
         .route("/", get(me))
         .route("/tokens", get(token::list))
         .route("/tokens", post(token::new))
-        .route("/tokens/:id", delete(token::revoke));
+        .route("/tokens/:id", delete(token::revoke))
+        .route("/trusted-publishers", get(trusted::list))
+        .route("/trusted-publishers", post(trusted::new))
+        .route("/trusted-publishers/:id", delete(trusted::delete));
 
     let plugins_routes = Router::with_state(state.clone())
         .route("/", get(plugin::search))
         .route("/new", put(plugin::publish))
+        .route("/updates", post(plugin::updates))
+        .route("/uploads", post(upload::create))
+        .route("/uploads/:id", get(upload::status))
+        .route("/uploads/:id", put(upload::part))
+        .route("/uploads/:id/finish", post(upload::finish))
         .route("/me/:name/:version/yank", put(plugin::yank))
         .route("/me/:name/:version/unyank", put(plugin::unyank))
+        .route("/:author/:name", delete(plugin::delete))
+        .route("/:author/:name/versions", get(plugin::versions))
+        .route("/:author/:name/downloads", get(plugin::downloads))
         .route("/:author/:name/:version", get(plugin::meta))
         .route("/:author/:name/:version/download", get(plugin::download))
+        .route("/:author/:name/:version/archive", get(plugin::archive))
         .route("/:author/:name/:version/readme", get(plugin::readme))
-        .route("/:author/:name/:version/icon", get(plugin::icon));
+        .route(
+            "/:author/:name/:version/readme.html",
+            get(plugin::readme_html),
+        )
+        .route("/:author/:name/:version/icon", get(plugin::icon))
+        .route("/:author/:name/:version/files/*path", get(plugin::file));
 
     let v1 = Router::with_state(state.clone())
+        .route("/trusted-publishing/token", post(trusted::exchange))
+        .route("/users/:login", get(user::profile))
         .nest("/me", user_routes)
         .nest("/plugins", plugins_routes);
 
@@ -58,20 +92,30 @@ pub fn build_router() -> Router<AppState> {
 async fn me(
     State(store): State<MemoryStore>,
     State(db_pool): State<DbPool>,
-    TypedHeader(cookies): TypedHeader<headers::Cookie>,
+    token: Option<TypedHeader<headers::Authorization<Bearer>>>,
+    cookies: Option<TypedHeader<headers::Cookie>>,
 ) -> impl IntoResponse {
-    let cookie = cookies.get(SESSION_COOKIE_NAME).unwrap();
-    let session = store
-        .load_session(cookie.to_string())
-        .await
-        .unwrap()
-        .unwrap();
-    let user_id: i32 = session.get("user_id").unwrap();
-    let mut conn = db_pool.read.get().await.unwrap();
-    let user = find_user(&mut conn, user_id).await.unwrap();
-    Json(MeUser {
-        login: user.gh_login,
-    })
+    let user = if let Some(TypedHeader(token)) = token {
+        let mut conn = db_pool.write.get().await.unwrap();
+        match find_api_token(&mut conn, token.token()).await {
+            Ok(api_token) => find_user(&mut conn, api_token.user_id).await.ok(),
+            Err(_) => {
+                return (StatusCode::UNAUTHORIZED, "API Token Invalid").into_response();
+            }
+        }
+    } else if let Some(cookies) = cookies {
+        authenticated_user(State(store), State(db_pool), cookies).await
+    } else {
+        None
+    };
+
+    match user {
+        Some(user) => Json(MeUser {
+            login: user.gh_login,
+        })
+        .into_response(),
+        None => (StatusCode::UNAUTHORIZED, "not logged in").into_response(),
+    }
 }
 
 async fn new_session(
@@ -81,6 +125,8 @@ async fn new_session(
     let (url, state) = github_oauth
         .authorize_url(oauth2::CsrfToken::new_random)
         .add_scope(Scope::new("read:user".to_string()))
+        // Lets publishing check that organization owners own a plugin's repository
+        .add_scope(Scope::new("read:org".to_string()))
         .url();
     let state = state.secret().to_string();
 
@@ -141,10 +187,16 @@ async fn session_authorize(
 
     let mut conn = db_pool.write.get().await.unwrap();
 
-    let user = NewUser::new(ghuser.id, &ghuser.login, token.secret())
-        .create_or_update(&mut conn)
-        .await
-        .unwrap();
+    let user = NewUser::new(
+        ghuser.id,
+        &ghuser.login,
+        token.secret(),
+        ghuser.avatar_url.as_deref(),
+        ghuser.name.as_deref(),
+    )
+    .create_or_update(&mut conn)
+    .await
+    .unwrap();
 
     session.insert("user_id", user.id).unwrap();
 
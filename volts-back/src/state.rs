@@ -5,7 +5,10 @@ use axum::extract::FromRef;
 use oauth2::{basic::BasicClient, AuthUrl, ClientId, ClientSecret, TokenUrl};
 use s3::{creds::Credentials, Bucket, Region};
 
-use crate::{db::DbPool, github::GithubClient};
+use crate::{
+    db::DbPool, downloads::DownloadCounter, github::GithubClient, storage::Storage,
+    trusted::OidcVerifier, upload::UploadSessions,
+};
 
 const GITHUB_OAUTH_AUTHORIZE_ENDPOINT: &str = "https://github.com/login/oauth/authorize";
 const GITHUB_OAUTH_TOKEN_ENDPOINT: &str = "https://github.com/login/oauth/access_token";
@@ -19,7 +22,10 @@ pub struct AppState {
     pub github_oauth: BasicClient,
     github_client: GithubClient,
     db_pool: DbPool,
-    bucket: Bucket,
+    storage: Storage,
+    uploads: UploadSessions,
+    oidc_verifier: OidcVerifier,
+    downloads: DownloadCounter,
 }
 
 impl FromRef<AppState> for MemoryStore {
@@ -46,9 +52,27 @@ impl FromRef<AppState> for DbPool {
     }
 }
 
-impl FromRef<AppState> for Bucket {
+impl FromRef<AppState> for Storage {
     fn from_ref(state: &AppState) -> Self {
-        state.bucket.clone()
+        state.storage.clone()
+    }
+}
+
+impl FromRef<AppState> for UploadSessions {
+    fn from_ref(state: &AppState) -> Self {
+        state.uploads.clone()
+    }
+}
+
+impl FromRef<AppState> for OidcVerifier {
+    fn from_ref(state: &AppState) -> Self {
+        state.oidc_verifier.clone()
+    }
+}
+
+impl FromRef<AppState> for DownloadCounter {
+    fn from_ref(state: &AppState) -> Self {
+        state.downloads.clone()
     }
 }
 
@@ -97,7 +121,10 @@ impl AppState {
             github_oauth,
             github_client,
             db_pool,
-            bucket,
+            storage: Storage::new(bucket),
+            uploads: UploadSessions::default(),
+            oidc_verifier: OidcVerifier::new(),
+            downloads: DownloadCounter::new(),
         }
     }
 }
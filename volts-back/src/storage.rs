@@ -0,0 +1,230 @@
+//! Plugin files in the R2 bucket. Archives are either downloaded from R2 with
+//! presigned URLs, or streamed through the backend when `ARCHIVE_SERVE_MODE`
+//! is `stream`, for clients that can't reach the storage host.
+
+use std::future::Future;
+
+use anyhow::{anyhow, Result};
+use axum::{
+    body::StreamBody,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use s3::Bucket;
+use tokio::io::{AsyncWriteExt, DuplexStream};
+use tokio_util::io::ReaderStream;
+
+use crate::cache::{not_modified, IMMUTABLE};
+
+/// How long presigned archive URLs stay valid
+const PRESIGNED_URL_SECS: u32 = 60;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveMode {
+    Presigned,
+    Stream,
+}
+
+pub struct ObjectInfo {
+    pub content_length: u64,
+    pub content_type: Option<String>,
+    pub etag: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct Storage {
+    bucket: Bucket,
+    /// Fetches ranges of archives through presigned URLs
+    client: reqwest::Client,
+    pub archive_mode: ArchiveMode,
+}
+
+impl Storage {
+    pub fn new(bucket: Bucket) -> Self {
+        let archive_mode = match std::env::var("ARCHIVE_SERVE_MODE").as_deref() {
+            Ok("stream") => ArchiveMode::Stream,
+            _ => ArchiveMode::Presigned,
+        };
+        Self {
+            bucket,
+            client: reqwest::Client::new(),
+            archive_mode,
+        }
+    }
+
+    pub async fn put(&self, path: &str, content: &[u8], content_type: Option<&str>) -> Result<()> {
+        let resp = match content_type {
+            Some(content_type) => {
+                self.bucket
+                    .put_object_with_content_type(path, content, content_type)
+                    .await?
+            }
+            None => self.bucket.put_object(path, content).await?,
+        };
+        if resp.status_code() != 200 {
+            return Err(anyhow!("storing {path} failed with {}", resp.status_code()));
+        }
+        Ok(())
+    }
+
+    /// The content of the object, or `None` if there's no object at `path`.
+    pub async fn get(&self, path: &str) -> Result<Option<Vec<u8>>> {
+        let resp = self.bucket.get_object(path).await?;
+        match resp.status_code() {
+            200 => Ok(Some(resp.bytes().to_vec())),
+            404 => Ok(None),
+            code => Err(anyhow!("fetching {path} failed with {code}")),
+        }
+    }
+
+    pub async fn head(&self, path: &str) -> Result<Option<ObjectInfo>> {
+        let (head, code) = self.bucket.head_object(path).await?;
+        match code {
+            200 => Ok(Some(ObjectInfo {
+                content_length: head.content_length.unwrap_or(0).max(0) as u64,
+                content_type: head.content_type,
+                etag: head.e_tag,
+            })),
+            404 => Ok(None),
+            code => Err(anyhow!("looking up {path} failed with {code}")),
+        }
+    }
+
+    pub async fn exists(&self, path: &str) -> bool {
+        matches!(self.head(path).await, Ok(Some(_)))
+    }
+
+    /// Removes every object under `prefix`, ignoring failures.
+    pub async fn delete_prefix(&self, prefix: &str) {
+        if let Ok(pages) = self.bucket.list(prefix.to_string(), None).await {
+            for object in pages.into_iter().flat_map(|page| page.contents) {
+                let _ = self.bucket.delete_object(&object.key).await;
+            }
+        }
+    }
+
+    pub fn presigned_url(&self, path: &str) -> String {
+        self.bucket
+            .presign_get(path, PRESIGNED_URL_SECS, None)
+            .unwrap()
+    }
+
+    /// Streams an archive through the backend, answering `If-None-Match` and
+    /// single `Range` requests.
+    pub async fn serve_archive(&self, path: &str, headers: &HeaderMap) -> Response {
+        let info = match self.head(path).await {
+            Ok(Some(info)) => info,
+            Ok(None) => return (StatusCode::NOT_FOUND, "archive not found").into_response(),
+            Err(_) => {
+                return (StatusCode::INTERNAL_SERVER_ERROR, "can't download archive")
+                    .into_response()
+            }
+        };
+        let len = info.content_length;
+
+        let mut resp_headers = HeaderMap::new();
+        resp_headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/octet-stream"),
+        );
+        resp_headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+        resp_headers.insert(header::CACHE_CONTROL, HeaderValue::from_static(IMMUTABLE));
+        if let Some(etag) = info.etag.as_ref() {
+            if let Ok(value) = HeaderValue::from_str(etag) {
+                resp_headers.insert(header::ETAG, value);
+            }
+            if not_modified(headers, etag) {
+                return (StatusCode::NOT_MODIFIED, resp_headers).into_response();
+            }
+        }
+
+        if let Some(range) = headers
+            .get(header::RANGE)
+            .and_then(|range| range.to_str().ok())
+        {
+            let (start, end) = match parse_range(range, len) {
+                Some(range) => range,
+                None => {
+                    resp_headers.insert(
+                        header::CONTENT_RANGE,
+                        HeaderValue::from_str(&format!("bytes */{len}")).unwrap(),
+                    );
+                    return (StatusCode::RANGE_NOT_SATISFIABLE, resp_headers).into_response();
+                }
+            };
+            // The bucket client only reads ranges into memory, so the range
+            // is fetched through a presigned URL and streamed instead.
+            let mut resp = match self
+                .client
+                .get(self.presigned_url(path))
+                .header(reqwest::header::RANGE, format!("bytes={start}-{end}"))
+                .send()
+                .await
+            {
+                Ok(resp) if resp.status() == reqwest::StatusCode::PARTIAL_CONTENT => resp,
+                _ => {
+                    return (StatusCode::INTERNAL_SERVER_ERROR, "can't download archive")
+                        .into_response()
+                }
+            };
+            resp_headers.insert(
+                header::CONTENT_RANGE,
+                HeaderValue::from_str(&format!("bytes {start}-{end}/{len}")).unwrap(),
+            );
+            resp_headers.insert(header::CONTENT_LENGTH, HeaderValue::from(end - start + 1));
+            let body = streamed(|mut writer| async move {
+                while let Ok(Some(chunk)) = resp.chunk().await {
+                    if writer.write_all(&chunk).await.is_err() {
+                        break;
+                    }
+                }
+            });
+            return (StatusCode::PARTIAL_CONTENT, resp_headers, body).into_response();
+        }
+
+        let bucket = self.bucket.clone();
+        let path = path.to_string();
+        let body = streamed(|mut writer| async move {
+            let _ = bucket.get_object_stream(&path, &mut writer).await;
+        });
+        resp_headers.insert(header::CONTENT_LENGTH, HeaderValue::from(len));
+        (resp_headers, body).into_response()
+    }
+}
+
+/// A body streaming what `fill` writes, so archives are never held in memory.
+/// The client sees a short body if `fill` fails midway.
+fn streamed<F, Fut>(fill: F) -> StreamBody<ReaderStream<DuplexStream>>
+where
+    F: FnOnce(DuplexStream) -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let (writer, reader) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(fill(writer));
+    StreamBody::new(ReaderStream::new(reader))
+}
+
+/// Parses a single `bytes=` range into inclusive offsets within `len` bytes.
+fn parse_range(range: &str, len: u64) -> Option<(u64, u64)> {
+    let range = range.strip_prefix("bytes=")?.trim();
+    if range.contains(',') || len == 0 {
+        return None;
+    }
+    let (start, end) = range.split_once('-')?;
+    let (start, end) = if start.is_empty() {
+        let suffix: u64 = end.parse().ok()?;
+        (len.saturating_sub(suffix), len - 1)
+    } else {
+        let start: u64 = start.parse().ok()?;
+        let end = if end.is_empty() {
+            len - 1
+        } else {
+            end.parse::<u64>().ok()?.min(len - 1)
+        };
+        (start, end)
+    };
+    if start > end {
+        return None;
+    }
+    Some((start, end))
+}
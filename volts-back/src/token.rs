@@ -1,61 +1,115 @@
-use async_session::{MemoryStore, SessionStore};
+use async_session::MemoryStore;
 use axum::{
     extract::{Path, State},
-    response::IntoResponse,
+    http::StatusCode,
+    response::{IntoResponse, Response},
     Json, TypedHeader,
 };
-use volts_core::{ApiTokenList, NewTokenPayload};
+use headers::authorization::Bearer;
+use volts_core::{
+    db::models::User, ApiTokenList, NewTokenPayload, TOKEN_SCOPES, TOKEN_SCOPE_PUBLISH,
+    TOKEN_SCOPE_TOKENS, TOKEN_SCOPE_YANK,
+};
 
 use crate::{
-    db::{find_user, insert_token, list_tokens, revoke_token, DbPool},
+    db::{find_api_token, find_user, insert_token, list_tokens, revoke_token, DbPool},
     router::authenticated_user,
-    state::SESSION_COOKIE_NAME,
 };
 
+/// The user managing tokens, signed in with the session cookie or
+/// authenticated with an API token that has the tokens scope.
+pub(crate) async fn token_user(
+    store: MemoryStore,
+    db_pool: &DbPool,
+    token: Option<TypedHeader<headers::Authorization<Bearer>>>,
+    cookies: Option<TypedHeader<headers::Cookie>>,
+) -> Result<User, Response> {
+    if let Some(TypedHeader(token)) = token {
+        let mut conn = db_pool.write.get().await.unwrap();
+        let api_token = match find_api_token(&mut conn, token.token()).await {
+            Ok(api_token) => api_token,
+            Err(_) => return Err((StatusCode::UNAUTHORIZED, "API Token Invalid").into_response()),
+        };
+        if !api_token.has_scope(TOKEN_SCOPE_TOKENS) {
+            return Err((
+                StatusCode::FORBIDDEN,
+                "API Token doesn't have the tokens scope",
+            )
+                .into_response());
+        }
+        return Ok(find_user(&mut conn, api_token.user_id).await.unwrap());
+    }
+
+    let user = match cookies {
+        Some(cookies) => authenticated_user(State(store), State(db_pool.clone()), cookies).await,
+        None => None,
+    };
+    user.ok_or_else(|| (StatusCode::UNAUTHORIZED, "not logged in").into_response())
+}
+
 pub async fn list(
     State(store): State<MemoryStore>,
     State(db_pool): State<DbPool>,
-    TypedHeader(cookies): TypedHeader<headers::Cookie>,
+    token: Option<TypedHeader<headers::Authorization<Bearer>>>,
+    cookies: Option<TypedHeader<headers::Cookie>>,
 ) -> impl IntoResponse {
-    let cookie = cookies.get(SESSION_COOKIE_NAME).unwrap();
-    let session = store
-        .load_session(cookie.to_string())
-        .await
-        .unwrap()
-        .unwrap();
-    let user_id: i32 = session.get("user_id").unwrap();
+    let user = match token_user(store, &db_pool, token, cookies).await {
+        Ok(user) => user,
+        Err(resp) => return resp,
+    };
 
     let mut conn = db_pool.read.get().await.unwrap();
-    let user = find_user(&mut conn, user_id).await.unwrap();
     let tokens = list_tokens(&mut conn, &user).await.unwrap();
-    Json(ApiTokenList { api_tokens: tokens })
+    Json(ApiTokenList { api_tokens: tokens }).into_response()
 }
 
 pub async fn new(
     State(store): State<MemoryStore>,
     State(db_pool): State<DbPool>,
-    TypedHeader(cookies): TypedHeader<headers::Cookie>,
+    token: Option<TypedHeader<headers::Authorization<Bearer>>>,
+    cookies: Option<TypedHeader<headers::Cookie>>,
     Json(payload): Json<NewTokenPayload>,
 ) -> impl IntoResponse {
-    let user = authenticated_user(State(store), State(db_pool.clone()), TypedHeader(cookies))
+    let user = match token_user(store, &db_pool, token, cookies).await {
+        Ok(user) => user,
+        Err(resp) => return resp,
+    };
+
+    let scopes = payload.scopes.unwrap_or_else(|| {
+        vec![
+            TOKEN_SCOPE_PUBLISH.to_string(),
+            TOKEN_SCOPE_YANK.to_string(),
+        ]
+    });
+    if scopes.is_empty() {
+        return (StatusCode::BAD_REQUEST, "a token needs at least one scope").into_response();
+    }
+    if let Some(scope) = scopes.iter().find(|s| !TOKEN_SCOPES.contains(&s.as_str())) {
+        return (StatusCode::BAD_REQUEST, format!("unknown scope {scope}")).into_response();
+    }
+
+    let mut conn = db_pool.write.get().await.unwrap();
+    let token = insert_token(&mut conn, &user, &payload.name, &scopes)
         .await
         .unwrap();
-    let mut conn = db_pool.write.get().await.unwrap();
-
-    let token = insert_token(&mut conn, &user, &payload.name).await.unwrap();
-    Json(token)
+    Json(token).into_response()
 }
 
 pub async fn revoke(
     State(store): State<MemoryStore>,
     State(db_pool): State<DbPool>,
-    TypedHeader(cookies): TypedHeader<headers::Cookie>,
+    token: Option<TypedHeader<headers::Authorization<Bearer>>>,
+    cookies: Option<TypedHeader<headers::Cookie>>,
     Path(id): Path<i32>,
 ) -> impl IntoResponse {
-    let user = authenticated_user(State(store), State(db_pool.clone()), TypedHeader(cookies))
-        .await
-        .unwrap();
+    let user = match token_user(store, &db_pool, token, cookies).await {
+        Ok(user) => user,
+        Err(resp) => return resp,
+    };
 
     let mut conn = db_pool.write.get().await.unwrap();
-    revoke_token(&mut conn, &user, id).await.unwrap();
+    if !revoke_token(&mut conn, &user, id).await.unwrap() {
+        return (StatusCode::NOT_FOUND, "token not found").into_response();
+    }
+    ().into_response()
 }
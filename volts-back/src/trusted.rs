@@ -0,0 +1,404 @@
+//! Trusted publishing, where CI workflows publish a plugin without a stored
+//! API token. A workflow exchanges the OIDC identity token of its job for a
+//! short-lived token, when the plugin's owner trusts that workflow of the
+//! repository.
+
+use std::{
+    env,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, Result};
+use async_session::MemoryStore;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json, TypedHeader,
+};
+use headers::authorization::Bearer;
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, Algorithm, DecodingKey, Validation};
+use oauth2::AccessToken;
+use serde::Deserialize;
+use tokio::sync::RwLock;
+use volts_core::{
+    EncodeTrustedPublisher, NewTrustedPublisherPayload, TrustedPublisherList, TrustedToken,
+    TrustedTokenPayload,
+};
+
+use crate::{
+    db::{
+        delete_expired_tokens, delete_trusted_publisher, find_plugin, find_trusted_publishers,
+        find_user, insert_trusted_publisher, insert_trusted_token, list_trusted_publishers, DbPool,
+    },
+    github::GithubClient,
+    token::token_user,
+};
+
+const GITHUB_ACTIONS_ISSUER: &str = "https://token.actions.githubusercontent.com";
+const DEFAULT_AUDIENCE: &str = "lapce-volts";
+/// How long the keys fetched from the JWKS URL are used before fetching them again
+const JWKS_TTL: Duration = Duration::from_secs(10 * 60);
+/// How often tokens signed with an unknown key can make the keys be fetched
+/// again, since anyone can send those
+const JWKS_REFETCH_INTERVAL: Duration = Duration::from_secs(60);
+const JWKS_FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a token handed out to a trusted publisher can publish
+const TRUSTED_TOKEN_TTL_MINUTES: i64 = 15;
+
+/// Claims of a GitHub Actions OIDC identity token that identify the workflow.
+#[derive(Deserialize)]
+struct IdentityClaims {
+    /// `owner/repo`
+    repository: String,
+    repository_id: String,
+    repository_owner_id: String,
+    /// `owner/repo/.github/workflows/release.yml@refs/tags/v1.0.0`
+    job_workflow_ref: String,
+    environment: Option<String>,
+}
+
+impl IdentityClaims {
+    /// File name of the workflow, as long as it's a workflow of the repository
+    /// itself rather than a reusable workflow from elsewhere.
+    fn workflow(&self) -> Option<&str> {
+        let (path, _) = self.job_workflow_ref.split_once('@')?;
+        let prefix = format!("{}/.github/workflows/", self.repository);
+        if path.len() <= prefix.len() || !path[..prefix.len()].eq_ignore_ascii_case(&prefix) {
+            return None;
+        }
+        Some(&path[prefix.len()..])
+    }
+}
+
+/// Validates OIDC identity tokens against the issuer's published keys.
+/// `OIDC_JWKS_URL`, `OIDC_ISSUER` and `OIDC_AUDIENCE` default to GitHub
+/// Actions, and can point at a stand-in issuer for testing.
+#[derive(Clone)]
+pub struct OidcVerifier {
+    jwks_url: String,
+    issuer: String,
+    audience: String,
+    client: reqwest::Client,
+    jwks: Arc<RwLock<JwksCache>>,
+}
+
+#[derive(Default)]
+struct JwksCache {
+    keys: Option<(Instant, JwkSet)>,
+    /// When the keys were last fetched, or tried to be
+    last_fetch: Option<Instant>,
+}
+
+impl JwksCache {
+    fn find(&self, kid: &str, ttl: Duration) -> Option<Result<DecodingKey>> {
+        let (fetched_at, jwks) = self.keys.as_ref()?;
+        if fetched_at.elapsed() >= ttl {
+            return None;
+        }
+        let jwk = jwks.find(kid)?;
+        Some(DecodingKey::from_jwk(jwk).map_err(Into::into))
+    }
+}
+
+impl Default for OidcVerifier {
+    fn default() -> Self {
+        OidcVerifier::new()
+    }
+}
+
+impl OidcVerifier {
+    pub fn new() -> Self {
+        let issuer = env::var("OIDC_ISSUER").unwrap_or_else(|_| GITHUB_ACTIONS_ISSUER.to_string());
+        let jwks_url = env::var("OIDC_JWKS_URL")
+            .unwrap_or_else(|_| format!("{}/.well-known/jwks", issuer.trim_end_matches('/')));
+        let audience = env::var("OIDC_AUDIENCE").unwrap_or_else(|_| DEFAULT_AUDIENCE.to_string());
+        Self {
+            jwks_url,
+            issuer,
+            audience,
+            client: reqwest::Client::new(),
+            jwks: Arc::new(RwLock::new(JwksCache::default())),
+        }
+    }
+
+    async fn key(&self, kid: &str) -> Result<DecodingKey> {
+        if let Some(key) = self.jwks.read().await.find(kid, JWKS_TTL) {
+            return key;
+        }
+
+        let mut cache = self.jwks.write().await;
+        // Another request may have fetched the keys meanwhile.
+        if let Some(key) = cache.find(kid, JWKS_TTL) {
+            return key;
+        }
+        // Unknown keys refetch the set, the issuer may have rotated them, but
+        // only so often. Until then expired keys are still used.
+        if let Some(last_fetch) = cache.last_fetch {
+            if last_fetch.elapsed() < JWKS_REFETCH_INTERVAL {
+                return match cache.find(kid, Duration::MAX) {
+                    Some(key) => key,
+                    None => bail!("unknown signing key {kid}"),
+                };
+            }
+        }
+
+        cache.last_fetch = Some(Instant::now());
+        let jwks: JwkSet = self
+            .client
+            .get(&self.jwks_url)
+            // Other verifications wait for the fetch
+            .timeout(JWKS_FETCH_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let key = jwks.find(kid).map(DecodingKey::from_jwk);
+        cache.keys = Some((Instant::now(), jwks));
+        match key {
+            Some(key) => Ok(key?),
+            None => bail!("unknown signing key {kid}"),
+        }
+    }
+
+    async fn verify(&self, jwt: &str) -> Result<IdentityClaims> {
+        let header = decode_header(jwt)?;
+        if matches!(
+            header.alg,
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+        ) {
+            bail!("identity tokens have to be signed with a public key");
+        }
+        let kid = header
+            .kid
+            .ok_or_else(|| anyhow!("signing key id missing"))?;
+        let key = self.key(&kid).await?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_audience(&[&self.audience]);
+        Ok(decode::<IdentityClaims>(jwt, &key, &validation)?.claims)
+    }
+}
+
+/// Exchanges an OIDC identity token from CI for a short-lived token that can
+/// only publish the plugin whose trusted publisher matches the workflow.
+/// Repositories are matched by id, so a renamed or re-created repository of
+/// the same name isn't trusted. Trusted publishers added before the ids were
+/// recorded don't match and have to be added again.
+pub async fn exchange(
+    State(db_pool): State<DbPool>,
+    State(verifier): State<OidcVerifier>,
+    Json(payload): Json<TrustedTokenPayload>,
+) -> impl IntoResponse {
+    let claims = match verifier.verify(&payload.jwt).await {
+        Ok(claims) => claims,
+        Err(e) => {
+            return (
+                StatusCode::UNAUTHORIZED,
+                format!("identity token invalid: {e}"),
+            )
+                .into_response()
+        }
+    };
+    let workflow = match claims.workflow() {
+        Some(workflow) => workflow,
+        None => {
+            return (
+                StatusCode::FORBIDDEN,
+                "only workflows of the repository itself can publish",
+            )
+                .into_response()
+        }
+    };
+
+    let mut conn = db_pool.write.get().await.unwrap();
+    // Piggybacks on the exchange rather than running on a timer, since expired
+    // tokens can't be used anyway
+    delete_expired_tokens(&mut conn).await.unwrap();
+
+    let mut publishers: Vec<_> = find_trusted_publishers(
+        &mut conn,
+        &claims.repository_id,
+        &claims.repository_owner_id,
+    )
+    .await
+    .unwrap()
+    .into_iter()
+    .filter(|(publisher, plugin)| {
+        publisher.workflow == workflow
+            && (publisher.environment.is_none() || publisher.environment == claims.environment)
+            && payload
+                .plugin
+                .as_ref()
+                .map(|name| name.to_lowercase() == plugin.name)
+                .unwrap_or(true)
+    })
+    .collect();
+    let plugin = match publishers.len() {
+        0 => {
+            return (
+                StatusCode::FORBIDDEN,
+                format!(
+                    "no trusted publisher for workflow {workflow} of {}",
+                    claims.repository
+                ),
+            )
+                .into_response()
+        }
+        1 => publishers.pop().unwrap().1,
+        _ => {
+            return (
+                StatusCode::BAD_REQUEST,
+                "several plugins trust this workflow, name the plugin to publish",
+            )
+                .into_response()
+        }
+    };
+
+    let user = find_user(&mut conn, plugin.user_id).await.unwrap();
+    let expires_at =
+        chrono::Utc::now().naive_utc() + chrono::Duration::minutes(TRUSTED_TOKEN_TTL_MINUTES);
+    let token = insert_trusted_token(
+        &mut conn,
+        &plugin,
+        &format!("trusted publishing from {}", claims.repository),
+        expires_at,
+    )
+    .await
+    .unwrap();
+
+    Json(TrustedToken {
+        token: token.plaintext,
+        author: user.gh_login,
+        plugin: plugin.name,
+        expires_at,
+    })
+    .into_response()
+}
+
+pub async fn list(
+    State(store): State<MemoryStore>,
+    State(db_pool): State<DbPool>,
+    token: Option<TypedHeader<headers::Authorization<Bearer>>>,
+    cookies: Option<TypedHeader<headers::Cookie>>,
+) -> impl IntoResponse {
+    let user = match token_user(store, &db_pool, token, cookies).await {
+        Ok(user) => user,
+        Err(resp) => return resp,
+    };
+
+    let mut conn = db_pool.read.get().await.unwrap();
+    let publishers = list_trusted_publishers(&mut conn, &user).await.unwrap();
+    Json(TrustedPublisherList {
+        trusted_publishers: publishers
+            .into_iter()
+            .map(|(publisher, plugin)| EncodeTrustedPublisher {
+                publisher,
+                plugin: plugin.name,
+            })
+            .collect(),
+    })
+    .into_response()
+}
+
+/// The repository is looked up on GitHub with the user's access token, to
+/// record its id and check that the user can push to it, so only workflows
+/// the user could change themselves are trusted.
+pub async fn new(
+    State(store): State<MemoryStore>,
+    State(db_pool): State<DbPool>,
+    State(github_client): State<GithubClient>,
+    token: Option<TypedHeader<headers::Authorization<Bearer>>>,
+    cookies: Option<TypedHeader<headers::Cookie>>,
+    Json(payload): Json<NewTrustedPublisherPayload>,
+) -> impl IntoResponse {
+    let user = match token_user(store, &db_pool, token, cookies).await {
+        Ok(user) => user,
+        Err(resp) => return resp,
+    };
+
+    let (owner, repo) = match payload.repository.trim_matches('/').split_once('/') {
+        Some((owner, repo)) if !owner.is_empty() && !repo.is_empty() && !repo.contains('/') => {
+            (owner, repo)
+        }
+        _ => {
+            return (
+                StatusCode::BAD_REQUEST,
+                "repository has to be given as owner/repo",
+            )
+                .into_response()
+        }
+    };
+    if payload.workflow.is_empty() || payload.workflow.contains('/') {
+        return (
+            StatusCode::BAD_REQUEST,
+            "workflow has to be the file name of a workflow in .github/workflows",
+        )
+            .into_response();
+    }
+
+    let auth = AccessToken::new(user.gh_access_token.clone());
+    let repository = match github_client.repository(owner, repo, &auth).await {
+        Ok(repository) => repository,
+        Err(_) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                format!("repository {owner}/{repo} not found on GitHub"),
+            )
+                .into_response()
+        }
+    };
+    if !github_client.has_publish_access(&repository, &auth).await {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("you need push access to {owner}/{repo} to trust its workflows"),
+        )
+            .into_response();
+    }
+
+    let mut conn = db_pool.write.get().await.unwrap();
+    let plugin = match find_plugin(&mut conn, &user, &payload.plugin.to_lowercase()).await {
+        Ok(plugin) => plugin,
+        Err(_) => return (StatusCode::BAD_REQUEST, "plugin not found").into_response(),
+    };
+    let publisher = insert_trusted_publisher(
+        &mut conn,
+        &plugin,
+        &repository,
+        &payload.workflow,
+        payload.environment.as_deref(),
+    )
+    .await
+    .unwrap();
+
+    Json(EncodeTrustedPublisher {
+        publisher,
+        plugin: plugin.name,
+    })
+    .into_response()
+}
+
+pub async fn delete(
+    State(store): State<MemoryStore>,
+    State(db_pool): State<DbPool>,
+    token: Option<TypedHeader<headers::Authorization<Bearer>>>,
+    cookies: Option<TypedHeader<headers::Cookie>>,
+    Path(id): Path<i32>,
+) -> impl IntoResponse {
+    let user = match token_user(store, &db_pool, token, cookies).await {
+        Ok(user) => user,
+        Err(resp) => return resp,
+    };
+
+    let mut conn = db_pool.write.get().await.unwrap();
+    if !delete_trusted_publisher(&mut conn, &user, id)
+        .await
+        .unwrap()
+    {
+        return (StatusCode::NOT_FOUND, "trusted publisher not found").into_response();
+    }
+    ().into_response()
+}
@@ -0,0 +1,303 @@
+use std::{
+    collections::HashMap,
+    io::SeekFrom,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{BodyStream, Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json, TypedHeader,
+};
+use futures::TryStreamExt;
+use headers::authorization::Bearer;
+use serde::Deserialize;
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+use volts_core::{db::models::User, PublishPayload, UploadSession, TOKEN_SCOPE_PUBLISH};
+
+use crate::{
+    db::{find_api_token, find_user, DbPool},
+    github::GithubClient,
+    plugin::{publish_archive, release_notes},
+    storage::Storage,
+    util::generate_secure_alphanumeric_string,
+};
+
+const UPLOAD_ARCHIVE: &str = "plugin.volt";
+/// Unfinished uploads are dropped after a day
+const UPLOAD_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+/// How often expired uploads are removed from disk
+const SWEEP_INTERVAL: Duration = Duration::from_secs(10 * 60);
+/// The same as nginx's `client_max_body_size`
+const DEFAULT_MAX_UPLOAD_SIZE: u64 = 100 * 1024 * 1024;
+/// Unfinished uploads a user can have at once, so parts staged on disk are
+/// bounded
+const DEFAULT_MAX_UPLOADS_PER_USER: usize = 3;
+
+struct Upload {
+    user_id: i32,
+    created_at: Instant,
+    state: tokio::sync::Mutex<UploadState>,
+}
+
+struct UploadState {
+    dir: tempfile::TempDir,
+    received: u64,
+}
+
+/// Archives being uploaded in parts. Like the login sessions in `MemoryStore`
+/// they only live in this process.
+#[derive(Clone, Default)]
+pub struct UploadSessions {
+    uploads: Arc<Mutex<HashMap<String, Arc<Upload>>>>,
+}
+
+impl UploadSessions {
+    /// Starts an upload, or returns `None` if the user has too many open.
+    fn insert(&self, user_id: i32) -> Option<String> {
+        let mut uploads = self.uploads.lock().unwrap();
+        uploads.retain(|_, upload| upload.created_at.elapsed() < UPLOAD_TTL);
+        let open = uploads
+            .values()
+            .filter(|upload| upload.user_id == user_id)
+            .count();
+        if open >= max_uploads_per_user() {
+            return None;
+        }
+
+        let id = generate_secure_alphanumeric_string(32);
+        uploads.insert(
+            id.clone(),
+            Arc::new(Upload {
+                user_id,
+                created_at: Instant::now(),
+                state: tokio::sync::Mutex::new(UploadState {
+                    dir: tempfile::TempDir::new().unwrap(),
+                    received: 0,
+                }),
+            }),
+        );
+        Some(id)
+    }
+
+    fn get(&self, id: &str, user_id: i32) -> Option<Arc<Upload>> {
+        let uploads = self.uploads.lock().unwrap();
+        uploads
+            .get(id)
+            .filter(|upload| upload.user_id == user_id && upload.created_at.elapsed() < UPLOAD_TTL)
+            .cloned()
+    }
+
+    fn remove(&self, id: &str, user_id: i32) -> Option<Arc<Upload>> {
+        let upload = self.get(id, user_id)?;
+        self.uploads.lock().unwrap().remove(id);
+        Some(upload)
+    }
+
+    /// Removes expired uploads every few minutes, deleting their parts once
+    /// no request is writing to them.
+    pub fn spawn_sweep(&self) {
+        let uploads = self.uploads.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                uploads
+                    .lock()
+                    .unwrap()
+                    .retain(|_, upload| upload.created_at.elapsed() < UPLOAD_TTL);
+            }
+        });
+    }
+}
+
+fn max_upload_size() -> u64 {
+    std::env::var("MAX_UPLOAD_SIZE")
+        .ok()
+        .and_then(|size| size.parse().ok())
+        .unwrap_or(DEFAULT_MAX_UPLOAD_SIZE)
+}
+
+fn max_uploads_per_user() -> usize {
+    std::env::var("MAX_UPLOADS_PER_USER")
+        .ok()
+        .and_then(|max| max.parse().ok())
+        .unwrap_or(DEFAULT_MAX_UPLOADS_PER_USER)
+}
+
+/// The user publishing, and the plugin the token is limited to if any.
+async fn publisher(db_pool: &DbPool, token: &Bearer) -> Result<(User, Option<i32>), Response> {
+    let mut conn = db_pool.write.get().await.unwrap();
+    let api_token = match find_api_token(&mut conn, token.token()).await {
+        Ok(api_token) => api_token,
+        Err(_) => return Err((StatusCode::UNAUTHORIZED, "API Token Invalid").into_response()),
+    };
+    if !api_token.has_scope(TOKEN_SCOPE_PUBLISH) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "API Token doesn't have the publish scope",
+        )
+            .into_response());
+    }
+    let user = find_user(&mut conn, api_token.user_id).await.unwrap();
+    Ok((user, api_token.plugin_id))
+}
+
+fn upload_not_found() -> Response {
+    (StatusCode::NOT_FOUND, "upload not found").into_response()
+}
+
+pub async fn create(
+    State(db_pool): State<DbPool>,
+    State(uploads): State<UploadSessions>,
+    TypedHeader(token): TypedHeader<headers::Authorization<Bearer>>,
+) -> impl IntoResponse {
+    let (user, _) = match publisher(&db_pool, &token).await {
+        Ok(publisher) => publisher,
+        Err(resp) => return resp,
+    };
+
+    let id = match uploads.insert(user.id) {
+        Some(id) => id,
+        None => {
+            return (
+                StatusCode::TOO_MANY_REQUESTS,
+                "too many unfinished uploads, finish them or wait for them to expire",
+            )
+                .into_response()
+        }
+    };
+    Json(UploadSession { id, received: 0 }).into_response()
+}
+
+pub async fn status(
+    State(db_pool): State<DbPool>,
+    State(uploads): State<UploadSessions>,
+    TypedHeader(token): TypedHeader<headers::Authorization<Bearer>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let (user, _) = match publisher(&db_pool, &token).await {
+        Ok(publisher) => publisher,
+        Err(resp) => return resp,
+    };
+    let upload = match uploads.get(&id, user.id) {
+        Some(upload) => upload,
+        None => return upload_not_found(),
+    };
+
+    let received = upload.state.lock().await.received;
+    Json(UploadSession { id, received }).into_response()
+}
+
+#[derive(Deserialize)]
+pub struct PartQuery {
+    offset: u64,
+}
+
+/// Writes a part of the archive at `offset`, which can't be past what was
+/// received so far. Resending a part replaces it, and a part cut short keeps
+/// what arrived, so the client always continues from `received` in the
+/// response.
+pub async fn part(
+    State(db_pool): State<DbPool>,
+    State(uploads): State<UploadSessions>,
+    TypedHeader(token): TypedHeader<headers::Authorization<Bearer>>,
+    Path(id): Path<String>,
+    Query(query): Query<PartQuery>,
+    mut body: BodyStream,
+) -> impl IntoResponse {
+    let (user, _) = match publisher(&db_pool, &token).await {
+        Ok(publisher) => publisher,
+        Err(resp) => return resp,
+    };
+    let upload = match uploads.get(&id, user.id) {
+        Some(upload) => upload,
+        None => return upload_not_found(),
+    };
+
+    let mut state = upload.state.lock().await;
+    if query.offset > state.received {
+        return (
+            StatusCode::CONFLICT,
+            Json(UploadSession {
+                id,
+                received: state.received,
+            }),
+        )
+            .into_response();
+    }
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .open(state.dir.path().join(UPLOAD_ARCHIVE))
+        .await
+        .unwrap();
+    file.set_len(query.offset).await.unwrap();
+    file.seek(SeekFrom::Start(query.offset)).await.unwrap();
+    state.received = query.offset;
+
+    let max_size = max_upload_size();
+    // A broken connection ends the part early, keeping what arrived.
+    while let Ok(Some(chunk)) = body.try_next().await {
+        if state.received + chunk.len() as u64 > max_size {
+            file.set_len(query.offset).await.unwrap();
+            state.received = query.offset;
+            return (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!("plugin archive is larger than {max_size} bytes"),
+            )
+                .into_response();
+        }
+        file.write_all(&chunk).await.unwrap();
+        state.received += chunk.len() as u64;
+    }
+    file.flush().await.unwrap();
+
+    Json(UploadSession {
+        id,
+        received: state.received,
+    })
+    .into_response()
+}
+
+/// Publishes the uploaded archive, with the same validation as a single
+/// request publish. The body can give release notes.
+pub async fn finish(
+    State(db_pool): State<DbPool>,
+    State(storage): State<Storage>,
+    State(github_client): State<GithubClient>,
+    State(uploads): State<UploadSessions>,
+    TypedHeader(token): TypedHeader<headers::Authorization<Bearer>>,
+    Path(id): Path<String>,
+    payload: Option<Json<PublishPayload>>,
+) -> impl IntoResponse {
+    let notes = match release_notes(payload.and_then(|Json(payload)| payload.notes)) {
+        Ok(notes) => notes,
+        Err(resp) => return resp,
+    };
+    let (user, only_plugin) = match publisher(&db_pool, &token).await {
+        Ok(publisher) => publisher,
+        Err(resp) => return resp,
+    };
+    let upload = match uploads.remove(&id, user.id) {
+        Some(upload) => upload,
+        None => return upload_not_found(),
+    };
+
+    // Waits for a part still being written
+    let state = upload.state.lock().await;
+    publish_archive(
+        &db_pool,
+        &storage,
+        &github_client,
+        &user,
+        only_plugin,
+        &state.dir.path().join(UPLOAD_ARCHIVE),
+        notes,
+    )
+    .await
+}
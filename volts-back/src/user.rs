@@ -0,0 +1,51 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use volts_core::UserProfile;
+
+use crate::{
+    db::{find_user_by_gh_login, list_user_plugins, DbPool},
+    plugin::encode_plugin,
+};
+
+pub async fn profile(
+    State(db_pool): State<DbPool>,
+    Path(login): Path<String>,
+) -> impl IntoResponse {
+    let mut conn = db_pool.read.get().await.unwrap();
+    let user = match find_user_by_gh_login(&mut conn, &login).await {
+        Ok(user) => user,
+        Err(_) => return (StatusCode::NOT_FOUND, "user not found").into_response(),
+    };
+    let plugins = list_user_plugins(&mut conn, &user).await.unwrap();
+
+    let downloads = plugins
+        .iter()
+        .map(|(plugin, _)| plugin.downloads as i64)
+        .sum();
+    let mut plugins: Vec<_> = plugins
+        .into_iter()
+        .filter_map(|(plugin, versions)| {
+            let version = versions
+                .into_iter()
+                .filter(|v| !v.yanked)
+                .filter_map(|v| Some((semver::Version::parse(&v.num).ok()?, v)))
+                .max_by_key(|(num, _)| num.clone())?
+                .1;
+            Some(encode_plugin(plugin, user.gh_login.clone(), version))
+        })
+        .collect();
+    plugins.sort_by(|a, b| b.downloads.cmp(&a.downloads));
+
+    Json(UserProfile {
+        login: user.gh_login,
+        name: user.name,
+        avatar_url: user.gh_avatar,
+        downloads,
+        plugins,
+    })
+    .into_response()
+}
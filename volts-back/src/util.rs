@@ -1,5 +1,9 @@
+use std::net::IpAddr;
+
+use axum::http::{header, HeaderMap};
 use rand::{distributions::Uniform, rngs::OsRng, Rng};
 use sha2::{Digest, Sha256};
+use volts_core::db::models::User;
 
 pub struct SecureToken {
     plaintext: String,
@@ -30,7 +34,7 @@ impl SecureToken {
     }
 }
 
-fn generate_secure_alphanumeric_string(len: usize) -> String {
+pub(crate) fn generate_secure_alphanumeric_string(len: usize) -> String {
     const CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
 
     OsRng
@@ -39,3 +43,46 @@ fn generate_secure_alphanumeric_string(len: usize) -> String {
         .take(len)
         .collect()
 }
+
+/// Admins are listed by GitHub login in `VOLTS_ADMINS`, separated by commas.
+pub(crate) fn is_admin(user: &User) -> bool {
+    std::env::var("VOLTS_ADMINS")
+        .map(|admins| {
+            admins
+                .split(',')
+                .any(|admin| admin.trim().eq_ignore_ascii_case(&user.gh_login))
+        })
+        .unwrap_or(false)
+}
+
+/// The address of the client, from the header named by `CLIENT_IP_HEADER`.
+/// The server always runs behind a proxy, so without the header every request
+/// would come from the proxy's address and this is `None`.
+///
+/// The header has to be one the trusted proxy in front sets itself, like
+/// `X-Real-IP` from `proxy_set_header X-Real-IP $remote_addr` in nginx or
+/// `Fly-Client-IP` on fly.io, since clients can send any header. For a list
+/// like `X-Forwarded-For` the last address is used, which is the one the
+/// proxy appended, as the ones before it come from the client.
+pub(crate) fn client_ip(headers: &HeaderMap) -> Option<IpAddr> {
+    let name = std::env::var("CLIENT_IP_HEADER").ok()?;
+    let value = headers.get(name.as_str())?.to_str().ok()?;
+    value.rsplit(',').next()?.trim().parse().ok()
+}
+
+/// Absolute URL of `path` on this server, from `PUBLIC_URL` or else the
+/// request's `Host`.
+pub(crate) fn public_url(headers: &HeaderMap, path: &str) -> String {
+    if let Ok(base) = std::env::var("PUBLIC_URL") {
+        return format!("{}{path}", base.trim_end_matches('/'));
+    }
+    let host = headers
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .unwrap_or("localhost");
+    let scheme = headers
+        .get("x-forwarded-proto")
+        .and_then(|proto| proto.to_str().ok())
+        .unwrap_or("http");
+    format!("{scheme}://{host}{path}")
+}
@@ -0,0 +1,184 @@
+//! Responses of the registry API, mirroring the types in `volts-core`.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct EncodePlugin {
+    pub name: String,
+    pub author: String,
+    pub version: String,
+    pub display_name: String,
+    pub description: String,
+    pub downloads: i32,
+    pub repository: Option<String>,
+    #[serde(default)]
+    pub repository_verified: bool,
+    pub updated_at: String,
+    pub released_at: String,
+    pub wasm: bool,
+    #[serde(default)]
+    pub checksum: Option<String>,
+    #[serde(default)]
+    pub deprecated: bool,
+    #[serde(default)]
+    pub deprecation_message: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct PluginList {
+    pub total: i64,
+    pub limit: usize,
+    pub offset: usize,
+    pub plugins: Vec<EncodePlugin>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct EncodeVersion {
+    pub num: String,
+    pub yanked: bool,
+    pub downloads: i32,
+    pub released_at: String,
+    #[serde(default)]
+    pub checksum: Option<String>,
+    #[serde(default)]
+    pub yank_reason: Option<String>,
+    #[serde(default)]
+    pub release_notes: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct VersionList {
+    pub versions: Vec<EncodeVersion>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct PluginVersionId {
+    pub author: String,
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct UpdateCheckPayload {
+    pub plugins: Vec<PluginVersionId>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct PluginUpdate {
+    pub author: String,
+    pub name: String,
+    pub version: String,
+    pub yanked: bool,
+    #[serde(default)]
+    pub yank_reason: Option<String>,
+    pub latest: Option<EncodeVersion>,
+    #[serde(default)]
+    pub release_notes: Vec<ReleaseNotes>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub(crate) struct ReleaseNotes {
+    pub num: String,
+    pub notes: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct UpdateCheckList {
+    pub plugins: Vec<PluginUpdate>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct ApiToken {
+    pub id: i32,
+    pub name: String,
+    pub created_at: String,
+    pub last_used_at: Option<String>,
+    /// `None` for tokens created before scopes existed, which can publish and yank
+    #[serde(default)]
+    pub endpoint_scopes: Option<Vec<String>>,
+}
+
+impl ApiToken {
+    pub fn scopes(&self) -> Vec<String> {
+        self.endpoint_scopes
+            .clone()
+            .unwrap_or_else(|| vec!["publish".to_string(), "yank".to_string()])
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct ApiTokenList {
+    pub api_tokens: Vec<ApiToken>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct EncodeApiToken {
+    pub token: ApiToken,
+    pub plaintext: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct NewTokenPayload {
+    pub name: String,
+    pub scopes: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct UploadSession {
+    pub id: String,
+    pub received: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct EncodeTrustedPublisher {
+    pub id: i32,
+    pub plugin: String,
+    pub repository: String,
+    pub workflow: String,
+    pub environment: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct TrustedPublisherList {
+    pub trusted_publishers: Vec<EncodeTrustedPublisher>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct NewTrustedPublisherPayload {
+    pub plugin: String,
+    pub repository: String,
+    pub workflow: String,
+    pub environment: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct TrustedTokenPayload {
+    pub jwt: String,
+    pub plugin: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct TrustedToken {
+    pub token: String,
+    pub author: String,
+    pub plugin: String,
+    pub expires_at: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct PluginDeleted {
+    pub author: String,
+    pub name: String,
+    pub reserved_until: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct PublishPayload {
+    pub notes: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct YankPayload {
+    pub reason: Option<String>,
+}
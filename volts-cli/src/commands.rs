@@ -1,174 +1,1184 @@
 use std::{
-    collections::HashSet,
-    fs::{self, File},
-    path::PathBuf,
+    collections::BTreeMap,
+    env,
+    fmt::{self, Display},
+    fs,
+    io::stdin,
+    path::{Path, PathBuf},
+    process::Command,
 };
 
-use lapce_rpc::plugin::VoltMetadata;
 use reqwest::{Method, StatusCode};
-use tar::Builder;
-use toml_edit::easy as toml;
-use zstd::Encoder;
+use serde::{Deserialize, Serialize};
 
-use crate::{auth_token, Cli, IconTheme};
+use crate::{
+    api::{
+        ApiTokenList, EncodeApiToken, EncodePlugin, EncodeTrustedPublisher, EncodeVersion,
+        NewTokenPayload, NewTrustedPublisherPayload, PluginDeleted, PluginList, PluginVersionId,
+        ReleaseNotes, TrustedPublisherList, TrustedToken, TrustedTokenPayload, UpdateCheckList,
+        UpdateCheckPayload, VersionList, YankPayload,
+    },
+    auth_token,
+    config::{self, Config, Registry},
+    credentials::{self, TokenStore},
+    given_token,
+    install::{self, plugin_id, InstalledPlugin, InstalledPlugins},
+    output::{table, CliError, Format},
+    package,
+    scaffold::{self, NewKind},
+    upload, version, Cli, PluginKind, SearchSort, TokenScope,
+};
+
+/// Audience the registry expects in identity tokens from CI
+const OIDC_AUDIENCE: &str = "lapce-volts";
+
+#[derive(Deserialize)]
+struct MeUser {
+    login: String,
+}
+
+/// The user the token belongs to.
+fn me(registry: &Registry, token: &str) -> Result<MeUser, CliError> {
+    let resp = reqwest::blocking::Client::new()
+        .request(Method::GET, registry.api_url("/me"))
+        .bearer_auth(token)
+        .send()?;
+    if resp.status() != StatusCode::OK {
+        return Err(CliError::from_response(resp));
+    }
+    Ok(resp.json()?)
+}
+
+#[derive(Serialize)]
+pub(crate) struct LoggedIn {
+    login: String,
+    /// Path of the credentials file, `None` when saved in the system credential store
+    credentials_file: Option<PathBuf>,
+}
+
+impl Display for LoggedIn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.credentials_file.as_ref() {
+            Some(path) => write!(
+                f,
+                "logged in as {}, token saved in {}",
+                self.login,
+                path.display()
+            ),
+            None => write!(
+                f,
+                "logged in as {}, token saved in system credential store",
+                self.login
+            ),
+        }
+    }
+}
+
+pub(crate) fn login(cli: &Cli) -> Result<LoggedIn, CliError> {
+    let registry = config::registry(cli)?;
+    let token = if let Some(token) = given_token(cli, &registry) {
+        token.trim().to_string()
+    } else {
+        eprintln!(
+            "Please paste the API Token you created on {}/account",
+            registry.url
+        );
+        let mut token = String::new();
+        stdin().read_line(&mut token)?;
+        token.trim().to_string()
+    };
+
+    if token.is_empty() {
+        return Err(CliError::Validation("Token cannot be empty".to_string()));
+    }
 
-pub(crate) fn publish(cli: &Cli) {
-    let token = auth_token(cli);
+    let user = me(&registry, &token)?;
 
-    let temp_dir = tempfile::tempdir().unwrap();
+    let credentials_file = match credentials::save_token(&registry, &token)? {
+        TokenStore::Keyring => None,
+        TokenStore::File(path) => Some(path),
+    };
+
+    Ok(LoggedIn {
+        login: user.login,
+        credentials_file,
+    })
+}
+
+#[derive(Serialize)]
+pub(crate) struct LoggedOut {
+    removed: bool,
+}
+
+impl Display for LoggedOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.removed {
+            f.write_str("logged out, saved API token removed")
+        } else {
+            f.write_str("no saved API token found")
+        }
+    }
+}
+
+pub(crate) fn logout(cli: &Cli) -> Result<LoggedOut, CliError> {
+    let registry = config::registry(cli)?;
+    let removed = credentials::delete_token(&registry)?;
+    Ok(LoggedOut { removed })
+}
+
+#[derive(Serialize)]
+pub(crate) struct Published {
+    name: String,
+    version: String,
+}
+
+impl Display for Published {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "plugin {} v{} published successfully",
+            self.name, self.version
+        )
+    }
+}
+
+pub(crate) fn publish(
+    cli: &Cli,
+    trusted: bool,
+    notes: Option<&str>,
+) -> Result<Published, CliError> {
+    let registry = config::registry(cli)?;
+
+    let temp_dir = tempfile::tempdir()?;
     let archive_path = temp_dir.path().join("plugin.volt");
 
-    {
-        let archive = File::create(&archive_path).unwrap();
-        let encoder = Encoder::new(archive, 0).unwrap().auto_finish();
-        let mut tar = Builder::new(encoder);
+    let volt = package::package(&archive_path)?;
+
+    let token = if trusted {
+        trusted_token(&registry, &volt.name)?.token
+    } else {
+        auth_token(cli, &registry)?
+    };
+
+    let pb = upload::progress_bar(archive_path.metadata()?.len(), cli.format == Format::Text);
+    let result = upload::upload(&registry, token.trim(), &archive_path, notes, &pb);
+    pb.finish_and_clear();
+    result?;
+
+    Ok(Published {
+        name: volt.name.to_lowercase(),
+        version: volt.version,
+    })
+}
+
+/// The OIDC identity token of the CI job, given in `VOLTS_OIDC_TOKEN` or
+/// requested from GitHub Actions, which needs the `id-token: write`
+/// permission for it.
+fn identity_token() -> Result<String, CliError> {
+    if let Ok(jwt) = env::var("VOLTS_OIDC_TOKEN") {
+        return Ok(jwt);
+    }
 
-        let volt_path = PathBuf::from("volt.toml");
-        if !volt_path.exists() {
-            eprintln!("volt.toml doesn't exist");
-            return;
+    let (url, request_token) = match (
+        env::var("ACTIONS_ID_TOKEN_REQUEST_URL"),
+        env::var("ACTIONS_ID_TOKEN_REQUEST_TOKEN"),
+    ) {
+        (Ok(url), Ok(request_token)) => (url, request_token),
+        _ => {
+            return Err(CliError::Auth(
+                "No identity token found, set VOLTS_OIDC_TOKEN or give the GitHub Actions job the id-token: write permission"
+                    .to_string(),
+            ))
         }
+    };
 
-        let s = fs::read_to_string(&volt_path).unwrap();
-        let volt: VoltMetadata = match toml::from_str(&s) {
-            Ok(volt) => volt,
-            Err(e) => {
-                eprintln!("volt.toml format invalid: {e}");
-                return;
-            }
-        };
+    #[derive(Deserialize)]
+    struct IdentityToken {
+        value: String,
+    }
 
-        tar.append_path(&volt_path).unwrap();
+    let resp = reqwest::blocking::Client::new()
+        .request(Method::GET, url)
+        .query(&[("audience", OIDC_AUDIENCE)])
+        .bearer_auth(request_token)
+        .send()?;
+    if resp.status() != StatusCode::OK {
+        return Err(CliError::from_response(resp));
+    }
+    Ok(resp.json::<IdentityToken>()?.value)
+}
 
-        if let Some(wasm) = volt.wasm.as_ref() {
-            let wasm_path = PathBuf::from(wasm);
-            if !wasm_path.exists() {
-                eprintln!("wasm {wasm} not found");
-                return;
-            }
+/// Exchanges the identity token of the CI job for a short-lived token that
+/// can publish the plugin.
+fn trusted_token(registry: &Registry, name: &str) -> Result<TrustedToken, CliError> {
+    let payload = TrustedTokenPayload {
+        jwt: identity_token()?,
+        plugin: Some(name.to_lowercase()),
+    };
+    let resp = reqwest::blocking::Client::new()
+        .request(Method::POST, registry.api_url("/trusted-publishing/token"))
+        .json(&payload)
+        .send()?;
+    match resp.status() {
+        StatusCode::OK => Ok(resp.json()?),
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(CliError::Auth(
+            resp.text()
+                .unwrap_or_else(|_| "identity token rejected".to_string()),
+        )),
+        _ => Err(CliError::from_response(resp)),
+    }
+}
 
-            tar.append_path(&wasm_path).unwrap();
-        } else if let Some(themes) = volt.color_themes.as_ref() {
-            if themes.is_empty() {
-                eprintln!("no color theme provided");
-                return;
-            }
-            for theme in themes {
-                let theme_path = PathBuf::from(theme);
-                if !theme_path.exists() {
-                    eprintln!("color theme {theme} not found");
-                    return;
-                }
+#[derive(Serialize)]
+pub(crate) struct Packaged {
+    name: String,
+    version: String,
+    path: PathBuf,
+}
 
-                tar.append_path(&theme_path).unwrap();
-            }
-        } else if let Some(themes) = volt.icon_themes.as_ref() {
-            if themes.is_empty() {
-                eprintln!("no icon theme provided");
-                return;
-            }
-            for theme in themes {
-                let theme_path = PathBuf::from(theme);
-                if !theme_path.exists() {
-                    eprintln!("icon theme {theme} not found");
-                    return;
-                }
+impl Display for Packaged {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "plugin {} v{} packaged into {}",
+            self.name,
+            self.version,
+            self.path.display()
+        )
+    }
+}
 
-                tar.append_path(&theme_path).unwrap();
-
-                let s = fs::read_to_string(&theme_path).unwrap();
-                let theme_config: IconTheme = match toml::from_str(&s) {
-                    Ok(config) => config,
-                    Err(_) => {
-                        eprintln!("icon theme {theme} format invalid");
-                        return;
-                    }
-                };
-
-                let mut icons = HashSet::new();
-                icons.extend(theme_config.icon_theme.ui.values());
-                icons.extend(theme_config.icon_theme.filename.values());
-                icons.extend(theme_config.icon_theme.foldername.values());
-                icons.extend(theme_config.icon_theme.extension.values());
-
-                let cwd = PathBuf::from(".");
-
-                for icon in icons {
-                    let icon_path = theme_path.parent().unwrap_or(&cwd).join(icon);
-                    if !icon_path.exists() {
-                        eprintln!("icon {icon} not found");
-                        return;
-                    }
-                    tar.append_path(&icon_path).unwrap();
-                }
-            }
+pub(crate) fn package(output: Option<&Path>) -> Result<Packaged, CliError> {
+    let path = output
+        .map(|path| path.to_path_buf())
+        .unwrap_or_else(|| PathBuf::from("plugin.volt"));
+    let volt = package::package(&path)?;
+    Ok(Packaged {
+        name: volt.name.to_lowercase(),
+        version: volt.version,
+        path,
+    })
+}
+
+#[derive(Serialize)]
+pub(crate) struct Created {
+    name: String,
+    kind: &'static str,
+    dir: PathBuf,
+    files: Vec<String>,
+}
+
+impl Display for Created {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "created {} plugin {} in {}",
+            self.kind,
+            self.name,
+            self.dir.display()
+        )?;
+        for file in &self.files {
+            writeln!(f, "  {file}")?;
+        }
+        if self.kind == NewKind::Wasm.as_str() {
+            write!(
+                f,
+                "build it with `cargo build --release` (needs the wasm32-wasi target) before `volts package`"
+            )
         } else {
-            eprintln!("not a valid plugin");
-            return;
+            write!(f, "run `volts package` in it to build the plugin archive")
         }
+    }
+}
+
+pub(crate) fn new(name: &str, kind: NewKind, author: Option<&str>) -> Result<Created, CliError> {
+    scaffold::validate_name(name)?;
+
+    let author = author
+        .map(|author| author.to_string())
+        .or_else(|| std::env::var("USER").ok())
+        .or_else(|| std::env::var("USERNAME").ok())
+        .unwrap_or_else(|| "author".to_string());
+    let files = scaffold::plugin_files(name, kind, &author)?;
 
-        let readme_path = PathBuf::from("README.md");
-        if readme_path.exists() {
-            tar.append_path(&readme_path).unwrap();
+    let dir = PathBuf::from(name);
+    scaffold::scaffold(&dir, &files)?;
+
+    Ok(Created {
+        name: name.to_string(),
+        kind: kind.as_str(),
+        dir,
+        files: files.into_iter().map(|(path, _)| path).collect(),
+    })
+}
+
+#[derive(Serialize)]
+pub(crate) struct VersionBumped {
+    name: String,
+    from: String,
+    to: String,
+    /// Git tag created for the new version
+    tag: Option<String>,
+}
+
+impl Display for VersionBumped {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "bumped {} from v{} to v{}",
+            self.name, self.from, self.to
+        )?;
+        if let Some(tag) = self.tag.as_ref() {
+            write!(f, ", committed and tagged {tag}")?;
         }
+        Ok(())
+    }
+}
 
-        if let Some(icon) = volt.icon.as_ref() {
-            let icon_path = PathBuf::from(icon);
-            if !icon_path.exists() {
-                eprintln!("icon not found at the specified path");
-                return;
-            }
-            tar.append_path(&icon_path).unwrap();
+fn git(args: &[&str]) -> Result<(), CliError> {
+    let output = Command::new("git").args(args).output()?;
+    if !output.status.success() {
+        return Err(CliError::Io(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+pub(crate) fn version(
+    cli: &Cli,
+    bump: Option<&str>,
+    pre: Option<&str>,
+    git_tag: bool,
+) -> Result<VersionBumped, CliError> {
+    let registry = config::registry(cli)?;
+    let volt = package::read_volt()?;
+    let next = version::next_version(&volt.version, bump, pre)?;
+
+    // Plugins are published under the login of the token's owner, whatever
+    // the author in volt.toml is
+    let token = auth_token(cli, &registry)?;
+    let login = me(&registry, token.trim())?.login;
+    let name = volt.name.to_lowercase();
+    let resp = reqwest::blocking::Client::new()
+        .request(
+            Method::GET,
+            registry.api_url(&format!("/plugins/{login}/{name}/versions")),
+        )
+        .send()?;
+    match resp.status() {
+        StatusCode::OK => {
+            let versions: VersionList = resp.json()?;
+            version::check_unpublished(
+                &next,
+                versions.versions.iter().map(|v| v.num.as_str()),
+                &registry.url,
+            )?;
+        }
+        // Never published
+        StatusCode::NOT_FOUND => {}
+        _ => return Err(CliError::from_response(resp)),
+    }
+
+    version::write_version(Path::new("volt.toml"), &next)?;
+
+    let tag = if git_tag {
+        let tag = format!("v{next}");
+        git(&["commit", "-m", &tag, "--", "volt.toml"])?;
+        git(&["tag", &tag])?;
+        Some(tag)
+    } else {
+        None
+    };
+
+    Ok(VersionBumped {
+        name,
+        from: volt.version,
+        to: next.to_string(),
+        tag,
+    })
+}
+
+#[derive(Serialize)]
+pub(crate) struct Yanked {
+    name: String,
+    version: String,
+    yanked: bool,
+    reason: Option<String>,
+}
+
+impl Display for Yanked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.yanked {
+            f.write_str("plugin version yanked successfully")
+        } else {
+            f.write_str("plugin version unyanked successfully")
+        }
+    }
+}
+
+pub(crate) fn yank(
+    cli: &Cli,
+    name: &str,
+    version: &str,
+    reason: Option<&str>,
+) -> Result<Yanked, CliError> {
+    modify_yank(cli, name, version, true, reason)
+}
+
+pub(crate) fn unyank(cli: &Cli, name: &str, version: &str) -> Result<Yanked, CliError> {
+    modify_yank(cli, name, version, false, None)
+}
+
+fn modify_yank(
+    cli: &Cli,
+    name: &str,
+    version: &str,
+    yanked: bool,
+    reason: Option<&str>,
+) -> Result<Yanked, CliError> {
+    let registry = config::registry(cli)?;
+    let token = auth_token(cli, &registry)?;
+
+    let action = if yanked { "yank" } else { "unyank" };
+    let mut req = reqwest::blocking::Client::new()
+        .request(
+            Method::PUT,
+            registry.api_url(&format!("/plugins/me/{name}/{version}/{action}")),
+        )
+        .bearer_auth(token.trim());
+    if let Some(reason) = reason {
+        req = req.json(&YankPayload {
+            reason: Some(reason.to_string()),
+        });
+    }
+    let resp = req.send()?;
+    if resp.status() != StatusCode::OK {
+        return Err(CliError::from_response(resp));
+    }
+
+    Ok(Yanked {
+        name: name.to_string(),
+        version: version.to_string(),
+        yanked,
+        reason: reason.map(|r| r.to_string()),
+    })
+}
+
+#[derive(Serialize)]
+#[serde(transparent)]
+pub(crate) struct Deleted(PluginDeleted);
+
+impl Display for Deleted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "deleted plugin {}/{}, its name is reserved until {}",
+            self.0.author, self.0.name, self.0.reserved_until
+        )
+    }
+}
+
+pub(crate) fn delete(cli: &Cli, plugin: &str, yes: bool) -> Result<Deleted, CliError> {
+    let (author, name, version) = parse_plugin_id(plugin)?;
+    if version.is_some() {
+        return Err(CliError::Validation(
+            "plugins are deleted with all their versions, yank a single version instead"
+                .to_string(),
+        ));
+    }
+    let registry = config::registry(cli)?;
+    let token = auth_token(cli, &registry)?;
+
+    if !yes {
+        eprintln!("This deletes every version of {author}/{name} and can't be undone.");
+        eprintln!("Type the plugin name to confirm:");
+        let mut confirm = String::new();
+        stdin().read_line(&mut confirm)?;
+        if confirm.trim().to_lowercase() != name {
+            return Err(CliError::Validation(
+                "the name didn't match, nothing was deleted".to_string(),
+            ));
         }
-        tar.finish().unwrap();
     }
 
     let resp = reqwest::blocking::Client::new()
-        .request(Method::PUT, "https://plugins.lapce.dev/api/v1/plugins/new")
+        .request(
+            Method::DELETE,
+            registry.api_url(&format!("/plugins/{author}/{name}")),
+        )
+        .bearer_auth(token.trim())
+        .send()?;
+    if resp.status() != StatusCode::OK {
+        return Err(CliError::from_response(resp));
+    }
+    Ok(Deleted(resp.json()?))
+}
+
+#[derive(Serialize)]
+#[serde(transparent)]
+pub(crate) struct Tokens(ApiTokenList);
+
+impl Display for Tokens {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows: Vec<Vec<String>> = self
+            .0
+            .api_tokens
+            .iter()
+            .map(|token| {
+                vec![
+                    token.id.to_string(),
+                    token.name.clone(),
+                    token.scopes().join(","),
+                    token.created_at.clone(),
+                    token
+                        .last_used_at
+                        .clone()
+                        .unwrap_or_else(|| "never".to_string()),
+                ]
+            })
+            .collect();
+        table(f, &["ID", "NAME", "SCOPES", "CREATED", "LAST USED"], &rows)
+    }
+}
+
+pub(crate) fn token_list(cli: &Cli) -> Result<Tokens, CliError> {
+    let registry = config::registry(cli)?;
+    let token = auth_token(cli, &registry)?;
+
+    let resp = reqwest::blocking::Client::new()
+        .request(Method::GET, registry.api_url("/me/tokens"))
+        .bearer_auth(token.trim())
+        .send()?;
+    if resp.status() != StatusCode::OK {
+        return Err(CliError::from_response(resp));
+    }
+    Ok(Tokens(resp.json()?))
+}
+
+#[derive(Serialize)]
+#[serde(transparent)]
+pub(crate) struct TokenCreated(EncodeApiToken);
+
+impl Display for TokenCreated {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "created token {} ({}) with scopes {}, it won't be shown again:",
+            self.0.token.name,
+            self.0.token.id,
+            self.0.token.scopes().join(", ")
+        )?;
+        write!(f, "{}", self.0.plaintext)
+    }
+}
+
+pub(crate) fn token_create(
+    cli: &Cli,
+    name: &str,
+    scopes: &[TokenScope],
+) -> Result<TokenCreated, CliError> {
+    let registry = config::registry(cli)?;
+    let token = auth_token(cli, &registry)?;
+
+    let payload = NewTokenPayload {
+        name: name.to_string(),
+        scopes: if scopes.is_empty() {
+            None
+        } else {
+            Some(scopes.iter().map(|s| s.as_str().to_string()).collect())
+        },
+    };
+    let resp = reqwest::blocking::Client::new()
+        .request(Method::POST, registry.api_url("/me/tokens"))
         .bearer_auth(token.trim())
-        .body(File::open(&archive_path).unwrap())
-        .send()
-        .unwrap();
-    if resp.status() == StatusCode::OK {
-        println!("plugin published successfully");
-        return;
+        .json(&payload)
+        .send()?;
+    if resp.status() != StatusCode::OK {
+        return Err(CliError::from_response(resp));
     }
+    Ok(TokenCreated(resp.json()?))
+}
 
-    eprintln!("{}", resp.text().unwrap());
+#[derive(Serialize)]
+pub(crate) struct TokenRevoked {
+    id: i32,
+}
+
+impl Display for TokenRevoked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "revoked token {}", self.id)
+    }
 }
 
-pub(crate) fn yank(cli: &Cli, name: &String, version: &String) {
-    let token = auth_token(cli);
+pub(crate) fn token_revoke(cli: &Cli, id: i32) -> Result<TokenRevoked, CliError> {
+    let registry = config::registry(cli)?;
+    let token = auth_token(cli, &registry)?;
 
     let resp = reqwest::blocking::Client::new()
         .request(
-            Method::PUT,
-            format!("https://plugins.lapce.dev/api/v1/plugins/me/{name}/{version}/yank"),
+            Method::DELETE,
+            registry.api_url(&format!("/me/tokens/{id}")),
         )
         .bearer_auth(token.trim())
-        .send()
-        .unwrap();
-    if resp.status() == StatusCode::OK {
-        println!("plugin version yanked successfully");
-    } else {
-        eprintln!("failed to yank plugin version: {}", resp.text().unwrap());
+        .send()?;
+    if resp.status() != StatusCode::OK {
+        return Err(CliError::from_response(resp));
     }
+    Ok(TokenRevoked { id })
 }
 
-pub(crate) fn unyank(cli: &Cli, name: &String, version: &String) {
-    let token = auth_token(cli);
+#[derive(Serialize)]
+#[serde(transparent)]
+pub(crate) struct TrustedPublishers(TrustedPublisherList);
+
+impl Display for TrustedPublishers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows: Vec<Vec<String>> = self
+            .0
+            .trusted_publishers
+            .iter()
+            .map(|publisher| {
+                vec![
+                    publisher.id.to_string(),
+                    publisher.plugin.clone(),
+                    publisher.repository.clone(),
+                    publisher.workflow.clone(),
+                    publisher
+                        .environment
+                        .clone()
+                        .unwrap_or_else(|| "any".to_string()),
+                    publisher.created_at.clone(),
+                ]
+            })
+            .collect();
+        table(
+            f,
+            &[
+                "ID",
+                "PLUGIN",
+                "REPOSITORY",
+                "WORKFLOW",
+                "ENVIRONMENT",
+                "CREATED",
+            ],
+            &rows,
+        )
+    }
+}
+
+pub(crate) fn trusted_publisher_list(cli: &Cli) -> Result<TrustedPublishers, CliError> {
+    let registry = config::registry(cli)?;
+    let token = auth_token(cli, &registry)?;
+
+    let resp = reqwest::blocking::Client::new()
+        .request(Method::GET, registry.api_url("/me/trusted-publishers"))
+        .bearer_auth(token.trim())
+        .send()?;
+    if resp.status() != StatusCode::OK {
+        return Err(CliError::from_response(resp));
+    }
+    Ok(TrustedPublishers(resp.json()?))
+}
+
+#[derive(Serialize)]
+#[serde(transparent)]
+pub(crate) struct TrustedPublisherAdded(EncodeTrustedPublisher);
+
+impl Display for TrustedPublisherAdded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "workflow {} of {} can now publish {} ({})",
+            self.0.workflow, self.0.repository, self.0.plugin, self.0.id
+        )?;
+        if let Some(environment) = &self.0.environment {
+            write!(f, " from the {environment} environment")?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn trusted_publisher_add(
+    cli: &Cli,
+    plugin: &str,
+    repository: &str,
+    workflow: &str,
+    environment: Option<&str>,
+) -> Result<TrustedPublisherAdded, CliError> {
+    let registry = config::registry(cli)?;
+    let token = auth_token(cli, &registry)?;
+
+    let payload = NewTrustedPublisherPayload {
+        plugin: plugin.to_string(),
+        repository: repository.to_string(),
+        workflow: workflow.to_string(),
+        environment: environment.map(|e| e.to_string()),
+    };
+    let resp = reqwest::blocking::Client::new()
+        .request(Method::POST, registry.api_url("/me/trusted-publishers"))
+        .bearer_auth(token.trim())
+        .json(&payload)
+        .send()?;
+    if resp.status() != StatusCode::OK {
+        return Err(CliError::from_response(resp));
+    }
+    Ok(TrustedPublisherAdded(resp.json()?))
+}
+
+#[derive(Serialize)]
+pub(crate) struct TrustedPublisherRemoved {
+    id: i32,
+}
+
+impl Display for TrustedPublisherRemoved {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "removed trusted publisher {}", self.id)
+    }
+}
+
+pub(crate) fn trusted_publisher_remove(
+    cli: &Cli,
+    id: i32,
+) -> Result<TrustedPublisherRemoved, CliError> {
+    let registry = config::registry(cli)?;
+    let token = auth_token(cli, &registry)?;
 
     let resp = reqwest::blocking::Client::new()
         .request(
-            Method::PUT,
-            format!("https://plugins.lapce.dev/api/v1/plugins/me/{name}/{version}/unyank"),
+            Method::DELETE,
+            registry.api_url(&format!("/me/trusted-publishers/{id}")),
         )
         .bearer_auth(token.trim())
-        .send()
-        .unwrap();
-    if resp.status() == StatusCode::OK {
-        println!("plugin version yanked successfully");
+        .send()?;
+    if resp.status() != StatusCode::OK {
+        return Err(CliError::from_response(resp));
+    }
+    Ok(TrustedPublisherRemoved { id })
+}
+
+#[derive(Serialize)]
+#[serde(transparent)]
+pub(crate) struct SearchResults(PluginList);
+
+impl Display for SearchResults {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows: Vec<Vec<String>> = self
+            .0
+            .plugins
+            .iter()
+            .map(|plugin| {
+                let mut description: String = plugin.description.chars().take(60).collect();
+                if description.len() < plugin.description.len() {
+                    description.push('…');
+                }
+                vec![
+                    format!("{}/{}", plugin.author, plugin.name),
+                    plugin.version.clone(),
+                    plugin.downloads.to_string(),
+                    description,
+                ]
+            })
+            .collect();
+        table(f, &["PLUGIN", "VERSION", "DOWNLOADS", "DESCRIPTION"], &rows)?;
+        write!(f, "\n{} of {} plugins", self.0.plugins.len(), self.0.total)
+    }
+}
+
+pub(crate) fn search(
+    cli: &Cli,
+    query: Option<&str>,
+    kind: Option<PluginKind>,
+    sort: Option<SearchSort>,
+    limit: usize,
+) -> Result<SearchResults, CliError> {
+    let registry = config::registry(cli)?;
+
+    let mut params = vec![("limit", limit.to_string())];
+    if let Some(query) = query {
+        params.push(("q", query.to_string()));
+    }
+    if let Some(kind) = kind {
+        params.push(("kind", kind.as_str().to_string()));
+    }
+    if let Some(sort) = sort {
+        params.push(("sort", sort.as_str().to_string()));
+    }
+
+    let resp = reqwest::blocking::Client::new()
+        .request(Method::GET, registry.api_url("/plugins"))
+        .query(&params)
+        .send()?;
+    if resp.status() != StatusCode::OK {
+        return Err(CliError::from_response(resp));
+    }
+
+    Ok(SearchResults(resp.json()?))
+}
+
+/// Splits a plugin given as `<author>/<name>[@version]`.
+pub(crate) fn parse_plugin_id(id: &str) -> Result<(String, String, Option<String>), CliError> {
+    let (id, version) = match id.split_once('@') {
+        Some((id, version)) => (id, Some(version.to_string())),
+        None => (id, None),
+    };
+    match id.split_once('/') {
+        Some((author, name)) if !author.is_empty() && !name.is_empty() => {
+            Ok((author.to_string(), name.to_lowercase(), version))
+        }
+        _ => Err(CliError::Validation(format!(
+            "plugin {id} should be given as <author>/<name>[@version]"
+        ))),
+    }
+}
+
+#[derive(Serialize)]
+pub(crate) struct PluginInfo {
+    plugin: EncodePlugin,
+    versions: Vec<EncodeVersion>,
+}
+
+impl Display for PluginInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let plugin = &self.plugin;
+        writeln!(
+            f,
+            "{} ({}/{}) v{}",
+            plugin.display_name, plugin.author, plugin.name, plugin.version
+        )?;
+        writeln!(f, "{}", plugin.description)?;
+        if plugin.deprecated {
+            match plugin.deprecation_message.as_ref() {
+                Some(message) => writeln!(f, "deprecated: {message}")?,
+                None => writeln!(f, "deprecated")?,
+            }
+        }
+        writeln!(f)?;
+        let kind = if plugin.wasm { "wasm" } else { "theme" };
+        writeln!(f, "{:<12}{kind}", "kind:")?;
+        if let Some(repository) = plugin.repository.as_ref() {
+            let verified = if plugin.repository_verified {
+                "verified"
+            } else {
+                "unverified"
+            };
+            writeln!(f, "{:<12}{repository} ({verified})", "repository:")?;
+        }
+        writeln!(f, "{:<12}{}", "downloads:", plugin.downloads)?;
+        writeln!(f, "{:<12}{}", "released:", plugin.released_at)?;
+        writeln!(f, "{:<12}{}", "updated:", plugin.updated_at)?;
+        writeln!(f)?;
+
+        let rows: Vec<Vec<String>> = self
+            .versions
+            .iter()
+            .map(|v| {
+                vec![
+                    v.num.clone(),
+                    v.released_at.clone(),
+                    v.downloads.to_string(),
+                    match (v.yanked, v.yank_reason.as_ref()) {
+                        (true, Some(reason)) => format!("yanked: {reason}"),
+                        (true, None) => "yanked".to_string(),
+                        (false, _) => "".to_string(),
+                    },
+                ]
+            })
+            .collect();
+        table(f, &["VERSION", "RELEASED", "DOWNLOADS", ""], &rows)?;
+
+        let notes = self
+            .versions
+            .iter()
+            .find(|v| v.num == plugin.version)
+            .and_then(|v| v.release_notes.as_ref());
+        if let Some(notes) = notes {
+            write!(f, "\nrelease notes of v{}:\n{notes}", plugin.version)?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn info(cli: &Cli, plugin: &str) -> Result<PluginInfo, CliError> {
+    let registry = config::registry(cli)?;
+    let (author, name, version) = parse_plugin_id(plugin)?;
+    let version = version.unwrap_or_else(|| "latest".to_string());
+
+    let client = reqwest::blocking::Client::new();
+    let resp = client
+        .request(
+            Method::GET,
+            registry.api_url(&format!("/plugins/{author}/{name}/{version}")),
+        )
+        .send()?;
+    if resp.status() != StatusCode::OK {
+        return Err(CliError::from_response(resp));
+    }
+    let plugin: EncodePlugin = resp.json()?;
+
+    let resp = client
+        .request(
+            Method::GET,
+            registry.api_url(&format!("/plugins/{author}/{name}/versions")),
+        )
+        .send()?;
+    if resp.status() != StatusCode::OK {
+        return Err(CliError::from_response(resp));
+    }
+    let versions: VersionList = resp.json()?;
+
+    Ok(PluginInfo {
+        plugin,
+        versions: versions.versions,
+    })
+}
+
+#[derive(Serialize)]
+pub(crate) struct Installed {
+    #[serde(flatten)]
+    plugin: InstalledPlugin,
+    /// Whether the archive matched the checksum published by the registry
+    verified: bool,
+}
+
+impl Display for Installed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "installed {}/{} v{} into {}",
+            self.plugin.author,
+            self.plugin.name,
+            self.plugin.version,
+            self.plugin.dir.display()
+        )?;
+        if !self.verified {
+            write!(f, " (no checksum published, archive not verified)")?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn install(
+    cli: &Cli,
+    plugin: &str,
+    plugins_dir: Option<&Path>,
+) -> Result<Installed, CliError> {
+    let registry = config::registry(cli)?;
+    let (author, name, version) = parse_plugin_id(plugin)?;
+    let plugins_dir = install::plugins_dir(plugins_dir)?;
+
+    let (plugin, verified) = install::install_plugin(
+        &registry,
+        &plugins_dir,
+        &author,
+        &name,
+        version.as_deref().unwrap_or("latest"),
+    )?;
+
+    let mut installed = InstalledPlugins::load()?;
+    installed
+        .plugins
+        .insert(plugin_id(&plugin.author, &plugin.name), plugin.clone());
+    installed.save()?;
+
+    Ok(Installed { plugin, verified })
+}
+
+#[derive(Serialize)]
+#[serde(transparent)]
+pub(crate) struct Uninstalled(InstalledPlugin);
+
+impl Display for Uninstalled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "uninstalled {}/{} v{}",
+            self.0.author, self.0.name, self.0.version
+        )
+    }
+}
+
+pub(crate) fn uninstall(plugin: &str) -> Result<Uninstalled, CliError> {
+    let (author, name, _) = parse_plugin_id(plugin)?;
+
+    let mut installed = InstalledPlugins::load()?;
+    let plugin = installed
+        .plugins
+        .remove(&plugin_id(&author, &name))
+        .ok_or_else(|| CliError::Validation(format!("{author}/{name} isn't installed by volts")))?;
+    if plugin.dir.exists() {
+        fs::remove_dir_all(&plugin.dir)?;
+    }
+    installed.save()?;
+
+    Ok(Uninstalled(plugin))
+}
+
+#[derive(Serialize)]
+pub(crate) struct UpdatedPlugin {
+    author: String,
+    name: String,
+    from: String,
+    to: String,
+    /// Of the versions after `from`, newest first
+    release_notes: Vec<ReleaseNotes>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct Updated {
+    updated: Vec<UpdatedPlugin>,
+    /// Installed versions that have been yanked and have no newer version
+    yanked: Vec<YankedPlugin>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct YankedPlugin {
+    author: String,
+    name: String,
+    version: String,
+    reason: Option<String>,
+}
+
+impl Display for Updated {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.updated.is_empty() {
+            f.write_str("all plugins are up to date")?;
+        } else {
+            let rows: Vec<Vec<String>> = self
+                .updated
+                .iter()
+                .map(|p| {
+                    vec![
+                        format!("{}/{}", p.author, p.name),
+                        p.from.clone(),
+                        p.to.clone(),
+                    ]
+                })
+                .collect();
+            table(f, &["PLUGIN", "FROM", "TO"], &rows)?;
+        }
+        for p in &self.updated {
+            for notes in &p.release_notes {
+                write!(
+                    f,
+                    "\n\n{}/{} v{}:\n{}",
+                    p.author, p.name, notes.num, notes.notes
+                )?;
+            }
+        }
+        for p in &self.yanked {
+            write!(
+                f,
+                "\nwarning: {}/{} v{} has been yanked",
+                p.author, p.name, p.version
+            )?;
+            if let Some(reason) = p.reason.as_ref() {
+                write!(f, ": {reason}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn update(plugins: &[String]) -> Result<Updated, CliError> {
+    let config = Config::load().map_err(CliError::Validation)?;
+    let mut installed = InstalledPlugins::load()?;
+
+    let selected: Vec<InstalledPlugin> = if plugins.is_empty() {
+        installed.plugins.values().cloned().collect()
     } else {
-        eprintln!("failed to yank plugin version: {}", resp.text().unwrap());
+        plugins
+            .iter()
+            .map(|plugin| {
+                let (author, name, _) = parse_plugin_id(plugin)?;
+                installed
+                    .plugins
+                    .get(&plugin_id(&author, &name))
+                    .cloned()
+                    .ok_or_else(|| {
+                        CliError::Validation(format!("{author}/{name} isn't installed by volts"))
+                    })
+            })
+            .collect::<Result<_, _>>()?
+    };
+
+    let mut by_registry: BTreeMap<String, Vec<InstalledPlugin>> = BTreeMap::new();
+    for plugin in selected {
+        // Leave plugins linked to a working copy by `volts dev` alone.
+        if install::is_dev(&plugin.dir) {
+            continue;
+        }
+        by_registry
+            .entry(plugin.registry.clone())
+            .or_default()
+            .push(plugin);
     }
+
+    let client = reqwest::blocking::Client::new();
+    let mut updated = Vec::new();
+    let mut yanked = Vec::new();
+    for (url, plugins) in by_registry {
+        let registry = config.registry(Some(&url)).map_err(CliError::Validation)?;
+        let payload = UpdateCheckPayload {
+            plugins: plugins
+                .iter()
+                .map(|p| PluginVersionId {
+                    author: p.author.clone(),
+                    name: p.name.clone(),
+                    version: p.version.clone(),
+                })
+                .collect(),
+        };
+        let resp = client
+            .request(Method::POST, registry.api_url("/plugins/updates"))
+            .json(&payload)
+            .send()?;
+        if resp.status() != StatusCode::OK {
+            return Err(CliError::from_response(resp));
+        }
+        let updates: UpdateCheckList = resp.json()?;
+
+        for update in updates.plugins {
+            let id = plugin_id(&update.author, &update.name);
+            let current = match plugins.iter().find(|p| plugin_id(&p.author, &p.name) == id) {
+                Some(current) => current,
+                None => continue,
+            };
+
+            match update.latest {
+                Some(latest) => {
+                    let plugins_dir = current.dir.parent().unwrap_or(&current.dir);
+                    let (plugin, _) = install::install_plugin(
+                        &registry,
+                        plugins_dir,
+                        &current.author,
+                        &current.name,
+                        &latest.num,
+                    )?;
+                    updated.push(UpdatedPlugin {
+                        author: plugin.author.clone(),
+                        name: plugin.name.clone(),
+                        from: current.version.clone(),
+                        to: plugin.version.clone(),
+                        release_notes: update.release_notes.clone(),
+                    });
+                    installed
+                        .plugins
+                        .insert(plugin_id(&current.author, &current.name), plugin);
+                    installed.save()?;
+                }
+                None if update.yanked => yanked.push(YankedPlugin {
+                    author: current.author.clone(),
+                    name: current.name.clone(),
+                    version: current.version.clone(),
+                    reason: update.yank_reason.clone(),
+                }),
+                None => {}
+            }
+        }
+    }
+
+    Ok(Updated { updated, yanked })
 }
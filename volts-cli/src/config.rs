@@ -0,0 +1,151 @@
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use directories::BaseDirs;
+use serde::{Deserialize, Serialize};
+use toml_edit::easy as toml;
+
+use crate::{output::CliError, Cli};
+
+pub(crate) const DEFAULT_REGISTRY_URL: &str = "https://plugins.lapce.dev";
+const DEFAULT_REGISTRY_NAME: &str = "default";
+const CONFIG_FILE: &str = "config.toml";
+
+/// Contents of `~/.config/volts/config.toml`, e.g.
+///
+/// ```toml
+/// default-registry = "staging"
+///
+/// [registries.staging]
+/// url = "https://staging.plugins.lapce.dev"
+///
+/// [registries.local]
+/// url = "http://localhost:8080"
+/// ```
+///
+/// `plugins-dir` overrides the Lapce plugins directory `volts install` uses.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct Config {
+    /// Registry used when neither `--registry` nor `VOLTS_REGISTRY` is set
+    pub default_registry: Option<String>,
+    pub plugins_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub registries: BTreeMap<String, RegistryConfig>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct RegistryConfig {
+    pub url: String,
+}
+
+/// The registry a command talks to.
+pub(crate) struct Registry {
+    /// Name the registry's token is stored under. `None` for the default
+    /// registry, the config name for named registries, and the URL itself for
+    /// registries given as a plain URL.
+    pub name: Option<String>,
+    pub url: String,
+}
+
+impl Registry {
+    pub fn api_url(&self, path: &str) -> String {
+        format!("{}/api/v1{path}", self.url.trim_end_matches('/'))
+    }
+
+    /// Environment variable the registry's token can be given in:
+    /// `VOLTS_TOKEN` for the default registry and `VOLTS_TOKEN_<NAME>` for
+    /// named ones, e.g. `VOLTS_TOKEN_STAGING`. Registries given as a plain
+    /// URL only take `--token` or a saved token.
+    pub fn token_env(&self) -> Option<String> {
+        match self.name.as_deref() {
+            None => Some("VOLTS_TOKEN".to_string()),
+            Some(name) if name.contains("://") => None,
+            Some(name) => {
+                let name: String = name
+                    .chars()
+                    .map(|c| {
+                        if c.is_ascii_alphanumeric() {
+                            c.to_ascii_uppercase()
+                        } else {
+                            '_'
+                        }
+                    })
+                    .collect();
+                Some(format!("VOLTS_TOKEN_{name}"))
+            }
+        }
+    }
+}
+
+pub(crate) fn config_dir() -> Option<PathBuf> {
+    BaseDirs::new().map(|dirs| dirs.home_dir().join(".config").join("volts"))
+}
+
+impl Config {
+    pub fn load() -> Result<Config, String> {
+        let path = match config_dir() {
+            Some(dir) => dir.join(CONFIG_FILE),
+            None => return Ok(Config::default()),
+        };
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+
+        let s =
+            fs::read_to_string(&path).map_err(|e| format!("can't read {}: {e}", path.display()))?;
+        toml::from_str(&s).map_err(|e| format!("{} format invalid: {e}", path.display()))
+    }
+
+    /// Resolves a registry given either as a URL or as a name from the
+    /// config file.
+    pub fn registry(&self, registry: Option<&str>) -> Result<Registry, String> {
+        let registry = match registry.or(self.default_registry.as_deref()) {
+            Some(registry) => registry,
+            None => return Ok(default_registry()),
+        };
+
+        if registry.contains("://") {
+            let url = registry.trim_end_matches('/');
+            if url == DEFAULT_REGISTRY_URL {
+                return Ok(default_registry());
+            }
+            // Share the stored token with a named registry pointing at the same URL.
+            let name = self
+                .registries
+                .iter()
+                .find(|(_, config)| config.url.trim_end_matches('/') == url)
+                .map(|(name, _)| name.to_string())
+                .unwrap_or_else(|| url.to_string());
+            return Ok(Registry {
+                name: Some(name),
+                url: url.to_string(),
+            });
+        }
+
+        match self.registries.get(registry) {
+            Some(config) => Ok(Registry {
+                name: Some(registry.to_string()),
+                url: config.url.trim_end_matches('/').to_string(),
+            }),
+            None if registry == DEFAULT_REGISTRY_NAME => Ok(default_registry()),
+            None => Err(format!(
+                "registry {registry} isn't defined in {CONFIG_FILE}"
+            )),
+        }
+    }
+}
+
+fn default_registry() -> Registry {
+    Registry {
+        name: None,
+        url: DEFAULT_REGISTRY_URL.to_string(),
+    }
+}
+
+/// Resolves the registry selected by `--registry`, `VOLTS_REGISTRY` or the
+/// config file.
+pub(crate) fn registry(cli: &Cli) -> Result<Registry, CliError> {
+    Config::load()
+        .and_then(|config| config.registry(cli.registry.as_deref()))
+        .map_err(CliError::Validation)
+}
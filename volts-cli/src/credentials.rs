@@ -0,0 +1,133 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use serde::{Deserialize, Serialize};
+use toml_edit::easy as toml;
+
+use crate::config::{config_dir, Registry};
+
+const KEYRING_SERVICE: &str = "lapce-volts";
+const KEYRING_USER: &str = "registry-api";
+const CREDENTIALS_FILE: &str = "credentials.toml";
+
+/// Contents of `~/.config/volts/credentials.toml`, used when the system
+/// credential store isn't available (e.g. headless CI containers).
+///
+/// The top level `token` belongs to the default registry, tokens for other
+/// registries are kept under `[registries.<name>]`.
+#[derive(Default, Serialize, Deserialize)]
+struct Credentials {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    registries: BTreeMap<String, RegistryCredentials>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct RegistryCredentials {
+    token: Option<String>,
+}
+
+impl Credentials {
+    fn token(&mut self, registry: &Registry) -> &mut Option<String> {
+        match registry.name.as_ref() {
+            Some(name) => &mut self.registries.entry(name.to_string()).or_default().token,
+            None => &mut self.token,
+        }
+    }
+}
+
+/// Where a token ended up being saved by [`save_token`].
+pub(crate) enum TokenStore {
+    Keyring,
+    File(PathBuf),
+}
+
+fn credentials_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(CREDENTIALS_FILE))
+}
+
+fn keyring_entry(registry: &Registry) -> keyring::Entry {
+    match registry.name.as_ref() {
+        Some(name) => keyring::Entry::new(KEYRING_SERVICE, &format!("{KEYRING_USER}:{name}")),
+        None => keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER),
+    }
+}
+
+fn read_credentials() -> Credentials {
+    credentials_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|s| toml::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn write_credentials(credentials: &Credentials) -> io::Result<PathBuf> {
+    let path = credentials_path()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "home directory not found"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&path)?;
+    #[cfg(unix)]
+    {
+        // The file may have been created earlier with wider permissions.
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+
+    let s =
+        toml::to_string(credentials).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    file.write_all(s.as_bytes())?;
+    Ok(path)
+}
+
+/// Looks up a saved token for the registry, first in the system credential
+/// store and then in the credentials file.
+pub(crate) fn load_token(registry: &Registry) -> Option<String> {
+    if let Ok(token) = keyring_entry(registry).get_password() {
+        return Some(token);
+    }
+
+    read_credentials().token(registry).clone()
+}
+
+/// Saves the token in the system credential store, falling back to the
+/// credentials file when no credential store is available.
+pub(crate) fn save_token(registry: &Registry, token: &str) -> io::Result<TokenStore> {
+    if keyring_entry(registry).set_password(token).is_ok() {
+        return Ok(TokenStore::Keyring);
+    }
+
+    let mut credentials = read_credentials();
+    *credentials.token(registry) = Some(token.to_string());
+    write_credentials(&credentials).map(TokenStore::File)
+}
+
+/// Removes the registry's token from both the credential store and the
+/// credentials file. Returns whether a token was found at all.
+pub(crate) fn delete_token(registry: &Registry) -> io::Result<bool> {
+    let mut deleted = keyring_entry(registry).delete_password().is_ok();
+
+    let mut credentials = read_credentials();
+    if credentials.token(registry).take().is_some() {
+        if let Some(name) = registry.name.as_ref() {
+            credentials.registries.remove(name);
+        }
+        write_credentials(&credentials)?;
+        deleted = true;
+    }
+
+    Ok(deleted)
+}
@@ -0,0 +1,118 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use serde::Serialize;
+
+use crate::{
+    install::{self, plugin_id, DEV_MARKER},
+    output::{print, print_error, CliError, Format},
+    package,
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Serialize)]
+pub(crate) struct Synced {
+    author: String,
+    name: String,
+    version: String,
+    dir: PathBuf,
+}
+
+impl Display for Synced {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "synced {}/{} v{} into {}, reload it in Lapce to pick up the changes",
+            self.author,
+            self.name,
+            self.version,
+            self.dir.display()
+        )
+    }
+}
+
+/// Modification times of the files the plugin in the current directory is
+/// built from. Files `volt.toml` refers to but that don't exist yet, like wasm
+/// that hasn't been built, are included so that their creation is noticed.
+fn snapshot() -> BTreeMap<PathBuf, Option<SystemTime>> {
+    let mut paths = vec![PathBuf::from("volt.toml"), PathBuf::from("README.md")];
+    if let Ok(volt) = package::read_volt() {
+        paths.extend(volt.wasm.iter().map(PathBuf::from));
+        paths.extend(volt.color_themes.iter().flatten().map(PathBuf::from));
+        paths.extend(volt.icon_themes.iter().flatten().map(PathBuf::from));
+        paths.extend(volt.icon.iter().map(PathBuf::from));
+        if let Ok(files) = package::plugin_files(&volt) {
+            paths.extend(files);
+        }
+    }
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+            (path, modified)
+        })
+        .collect()
+}
+
+/// Packages the plugin the same way `volts publish` does and unpacks it into
+/// the plugins directory, marked as linked to `source`.
+fn sync(plugins_dir: &Path, source: &Path) -> Result<Synced, CliError> {
+    let temp_dir = tempfile::tempdir()?;
+    let archive_path = temp_dir.path().join("plugin.volt");
+    let volt = package::package(&archive_path)?;
+
+    let archive = fs::read(&archive_path)?;
+    let dir = install::unpack(&archive, plugins_dir, &plugin_id(&volt.author, &volt.name))?;
+    fs::write(dir.join(DEV_MARKER), source.display().to_string())?;
+
+    Ok(Synced {
+        author: volt.author,
+        name: volt.name,
+        version: volt.version,
+        dir,
+    })
+}
+
+/// Links the plugin in the current directory into Lapce's plugins directory
+/// and keeps it in sync until interrupted. Failing syncs are reported and
+/// retried on the next change.
+pub(crate) fn dev(format: Format, plugins_dir: Option<&Path>) -> Result<(), CliError> {
+    let plugins_dir = install::plugins_dir(plugins_dir)?;
+    fs::create_dir_all(&plugins_dir)?;
+    let source = std::env::current_dir()?;
+
+    let mut last = snapshot();
+    let mut pending = true;
+    loop {
+        if pending {
+            match sync(&plugins_dir, &source) {
+                Ok(synced) => print(format, &synced),
+                Err(e) => print_error(format, &e),
+            }
+            pending = false;
+        }
+
+        thread::sleep(POLL_INTERVAL);
+        let mut current = snapshot();
+        if current != last {
+            // Wait for writers like cargo to finish before syncing.
+            loop {
+                last = current;
+                thread::sleep(POLL_INTERVAL);
+                current = snapshot();
+                if current == last {
+                    break;
+                }
+            }
+            pending = true;
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use directories::ProjectDirs;
+use reqwest::{Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tar::Archive;
+use toml_edit::easy as toml;
+use zstd::Decoder;
+
+use crate::{
+    api::EncodePlugin,
+    config::{config_dir, Config, Registry},
+    output::CliError,
+};
+
+const INSTALLED_FILE: &str = "installed.toml";
+/// File `volts dev` leaves in the plugin directories it links, holding the
+/// path of the plugin's source directory.
+pub(crate) const DEV_MARKER: &str = ".volts-dev";
+
+/// Plugins installed by `volts install`, kept in `~/.config/volts/installed.toml`
+/// and keyed by plugin id (`<author>.<name>`).
+#[derive(Default, Serialize, Deserialize)]
+pub(crate) struct InstalledPlugins {
+    #[serde(default)]
+    pub plugins: BTreeMap<String, InstalledPlugin>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct InstalledPlugin {
+    pub author: String,
+    pub name: String,
+    pub version: String,
+    pub checksum: Option<String>,
+    pub registry: String,
+    pub dir: PathBuf,
+}
+
+pub(crate) fn plugin_id(author: &str, name: &str) -> String {
+    format!("{author}.{name}")
+}
+
+impl InstalledPlugins {
+    fn path() -> Result<PathBuf, CliError> {
+        config_dir()
+            .map(|dir| dir.join(INSTALLED_FILE))
+            .ok_or_else(|| CliError::Io("home directory not found".to_string()))
+    }
+
+    pub fn load() -> Result<InstalledPlugins, CliError> {
+        let path = Self::path()?;
+        if !path.exists() {
+            return Ok(InstalledPlugins::default());
+        }
+
+        let s = fs::read_to_string(&path)?;
+        toml::from_str(&s)
+            .map_err(|e| CliError::Io(format!("{} format invalid: {e}", path.display())))
+    }
+
+    pub fn save(&self) -> Result<(), CliError> {
+        let path = Self::path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let s = toml::to_string(self).map_err(|e| CliError::Io(e.to_string()))?;
+        fs::write(path, s)?;
+        Ok(())
+    }
+}
+
+/// Lapce's plugins directory, unless overridden by `--plugins-dir`,
+/// `VOLTS_PLUGINS_DIR` or `plugins-dir` in the config file.
+pub(crate) fn plugins_dir(plugins_dir: Option<&Path>) -> Result<PathBuf, CliError> {
+    if let Some(dir) = plugins_dir {
+        return Ok(dir.to_path_buf());
+    }
+
+    if let Some(dir) = Config::load().map_err(CliError::Validation)?.plugins_dir {
+        return Ok(dir);
+    }
+
+    ProjectDirs::from("dev", "lapce", "Lapce-Stable")
+        .map(|dirs| dirs.data_local_dir().join("plugins"))
+        .ok_or_else(|| CliError::Io("can't find Lapce's plugins directory".to_string()))
+}
+
+/// Resolves the version through the registry, downloads and verifies its
+/// archive and unpacks it into `<plugins_dir>/<author>.<name>`.
+pub(crate) fn install_plugin(
+    registry: &Registry,
+    plugins_dir: &Path,
+    author: &str,
+    name: &str,
+    version: &str,
+) -> Result<(InstalledPlugin, bool), CliError> {
+    let client = reqwest::blocking::Client::new();
+
+    let resp = client
+        .request(
+            Method::GET,
+            registry.api_url(&format!("/plugins/{author}/{name}/{version}")),
+        )
+        .send()?;
+    if resp.status() != StatusCode::OK {
+        return Err(CliError::from_response(resp));
+    }
+    let plugin: EncodePlugin = resp.json()?;
+
+    // The download endpoint answers with a short-lived presigned URL of the archive.
+    let resp = client
+        .request(
+            Method::GET,
+            registry.api_url(&format!(
+                "/plugins/{}/{}/{}/download",
+                plugin.author, plugin.name, plugin.version
+            )),
+        )
+        .send()?;
+    if resp.status() != StatusCode::OK {
+        return Err(CliError::from_response(resp));
+    }
+    let url = resp.text()?;
+
+    let resp = client.request(Method::GET, url.trim()).send()?;
+    if resp.status() != StatusCode::OK {
+        return Err(CliError::from_response(resp));
+    }
+    let archive = resp.bytes()?;
+
+    let verified = match plugin.checksum.as_ref() {
+        Some(checksum) => {
+            let actual = format!("{:x}", Sha256::digest(&archive));
+            if &actual != checksum {
+                return Err(CliError::Validation(format!(
+                    "checksum mismatch for {}/{} v{}: expected {checksum}, got {actual}",
+                    plugin.author, plugin.name, plugin.version
+                )));
+            }
+            true
+        }
+        None => false,
+    };
+
+    let dir = unpack(
+        &archive,
+        plugins_dir,
+        &plugin_id(&plugin.author, &plugin.name),
+    )?;
+
+    Ok((
+        InstalledPlugin {
+            author: plugin.author,
+            name: plugin.name,
+            version: plugin.version,
+            checksum: plugin.checksum,
+            registry: registry.url.clone(),
+            dir,
+        },
+        verified,
+    ))
+}
+
+/// Unpacks a plugin archive into `<plugins_dir>/<id>`, replacing what was
+/// there only once the whole archive unpacked.
+pub(crate) fn unpack(archive: &[u8], plugins_dir: &Path, id: &str) -> Result<PathBuf, CliError> {
+    let dir = plugins_dir.join(id);
+    let unpack_dir = plugins_dir.join(format!(".{id}.download"));
+    if unpack_dir.exists() {
+        fs::remove_dir_all(&unpack_dir)?;
+    }
+    fs::create_dir_all(&unpack_dir)?;
+
+    let unpacked =
+        Decoder::new(archive).and_then(|decoder| Archive::new(decoder).unpack(&unpack_dir));
+    if let Err(e) = unpacked {
+        let _ = fs::remove_dir_all(&unpack_dir);
+        return Err(CliError::Validation(format!("plugin archive invalid: {e}")));
+    }
+
+    if dir.exists() {
+        fs::remove_dir_all(&dir)?;
+    }
+    fs::rename(&unpack_dir, &dir)?;
+    Ok(dir)
+}
+
+/// Whether the plugin directory is linked to a working copy by `volts dev`.
+pub(crate) fn is_dev(dir: &Path) -> bool {
+    dir.join(DEV_MARKER).exists()
+}
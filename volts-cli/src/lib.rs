@@ -1,8 +1,21 @@
+mod api;
 mod commands;
+mod config;
+mod credentials;
+mod dev;
+mod install;
+mod output;
+mod package;
+mod scaffold;
+mod upload;
+mod version;
 
-use std::{collections::HashMap, io::stdin};
+use std::{collections::HashMap, path::PathBuf};
 
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use config::Registry;
+use output::{fail, report, CliError, Format};
+use scaffold::NewKind;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize)]
@@ -25,54 +38,320 @@ struct Cli {
     #[command(subcommand)]
     command: Commands,
 
-    /// Registry API authentication token
-    #[clap(long, action)]
+    /// Registry API authentication token. Otherwise read from VOLTS_TOKEN for
+    /// the default registry and VOLTS_TOKEN_<NAME> for named registries
+    #[clap(long, global = true)]
     token: Option<String>,
+
+    /// Registry URL, or the name of a registry defined in ~/.config/volts/config.toml
+    #[clap(long, global = true, env = "VOLTS_REGISTRY")]
+    registry: Option<String>,
+
+    /// Output format
+    #[clap(long, global = true, value_enum, default_value_t = Format::Text)]
+    format: Format,
 }
 
 #[derive(Subcommand)]
 enum Commands {
+    /// Save an API token for the registry
+    Login {},
+    /// Remove the saved API token
+    Logout {},
     /// Publish plugin to registry
-    Publish {},
+    Publish {
+        /// Publish from CI as a trusted publisher, exchanging the job's OIDC
+        /// identity token for a short-lived API token
+        #[clap(long)]
+        trusted: bool,
+        /// Release notes of the version, its section of CHANGELOG.md by default
+        #[clap(long)]
+        notes: Option<String>,
+    },
+    /// Pack the plugin in the current directory into an archive without publishing it
+    Package {
+        /// Archive path, plugin.volt by default
+        #[clap(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Link the plugin in the current directory into Lapce and re-sync it on changes
+    Dev {
+        /// Lapce plugins directory to link into
+        #[clap(long, env = "VOLTS_PLUGINS_DIR")]
+        plugins_dir: Option<PathBuf>,
+    },
+    /// Create a new plugin in a directory of the same name
+    New {
+        name: String,
+        #[clap(long, value_enum, default_value_t = NewKind::Wasm)]
+        kind: NewKind,
+        /// Author written to volt.toml, your user name by default
+        #[clap(long)]
+        author: Option<String>,
+    },
+    /// Bump the version in volt.toml, checking it against the versions you published
+    Version {
+        /// major, minor, patch or an explicit version
+        bump: Option<String>,
+        /// Make the new version a pre-release with this tag, e.g. beta
+        #[clap(long)]
+        pre: Option<String>,
+        /// Commit volt.toml and tag the commit with the new version
+        #[clap(long)]
+        git_tag: bool,
+    },
     /// Yank version from registry
-    Yank { name: String, version: String },
+    Yank {
+        name: String,
+        version: String,
+        /// Why the version shouldn't be used, shown to those who have it installed
+        #[clap(long)]
+        reason: Option<String>,
+    },
     /// Undo yanking version from registry
     Unyank { name: String, version: String },
+    /// Delete a plugin and all its versions from the registry, given as <author>/<name>.
+    /// Owners can do so shortly after first publishing, admins at any time.
+    Delete {
+        plugin: String,
+        /// Don't ask to confirm by typing the plugin name
+        #[clap(long)]
+        yes: bool,
+    },
+    /// Manage API tokens, using a token with the tokens scope
+    Token {
+        #[command(subcommand)]
+        command: TokenCommands,
+    },
+    /// Manage the CI workflows trusted to publish plugins, using a token with the tokens scope
+    TrustedPublisher {
+        #[command(subcommand)]
+        command: TrustedPublisherCommands,
+    },
+    /// Search plugins in registry
+    Search {
+        query: Option<String>,
+        #[clap(long, value_enum)]
+        kind: Option<PluginKind>,
+        #[clap(long, value_enum)]
+        sort: Option<SearchSort>,
+        /// Maximum number of plugins to show
+        #[clap(long, default_value_t = 10)]
+        limit: usize,
+    },
+    /// Show plugin details, given as <author>/<name>[@version]
+    Info { plugin: String },
+    /// Install plugin into Lapce, given as <author>/<name>[@version]
+    Install {
+        plugin: String,
+        /// Lapce plugins directory to install into
+        #[clap(long, env = "VOLTS_PLUGINS_DIR")]
+        plugins_dir: Option<PathBuf>,
+    },
+    /// Remove plugin installed by volts, given as <author>/<name>
+    Uninstall { plugin: String },
+    /// Update plugins installed by volts, all of them if none are given
+    Update { plugins: Vec<String> },
 }
 
-pub fn cli() {
-    let cli = Cli::parse();
+#[derive(Subcommand)]
+enum TokenCommands {
+    /// List API tokens
+    List {},
+    /// Create an API token, printing it once
+    Create {
+        name: String,
+        /// Scope of the token, can be repeated. publish and yank when not given
+        #[clap(long = "scope", value_enum)]
+        scopes: Vec<TokenScope>,
+    },
+    /// Revoke an API token by id
+    Revoke { id: i32 },
+}
 
-    match &cli.command {
-        Commands::Publish {} => commands::publish(&cli),
-        Commands::Yank { name, version } => commands::yank(&cli, name, version),
-        Commands::Unyank { name, version } => commands::unyank(&cli, name, version),
+#[derive(Subcommand)]
+enum TrustedPublisherCommands {
+    /// List trusted publishers of your plugins
+    List {},
+    /// Trust a GitHub Actions workflow to publish a plugin
+    Add {
+        plugin: String,
+        /// GitHub repository as owner/repo
+        #[clap(long)]
+        repository: String,
+        /// File name of the workflow, e.g. release.yml
+        #[clap(long)]
+        workflow: String,
+        /// Only trust jobs running in this deployment environment
+        #[clap(long)]
+        environment: Option<String>,
+    },
+    /// Remove a trusted publisher by id
+    Remove { id: i32 },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum TokenScope {
+    Publish,
+    Yank,
+    Tokens,
+    Delete,
+}
+
+impl TokenScope {
+    fn as_str(&self) -> &'static str {
+        match self {
+            TokenScope::Publish => "publish",
+            TokenScope::Yank => "yank",
+            TokenScope::Tokens => "tokens",
+            TokenScope::Delete => "delete",
+        }
     }
 }
 
-fn auth_token(cli: &Cli) -> String {
-    if let Some(token) = &cli.token {
-        token.to_owned()
-    } else {
-        let api_credential = keyring::Entry::new("lapce-volts", "registry-api");
-        if let Ok(token) = api_credential.get_password() {
-            return token;
+#[derive(Clone, Copy, ValueEnum)]
+enum PluginKind {
+    Wasm,
+    Theme,
+}
+
+impl PluginKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            PluginKind::Wasm => "wasm",
+            PluginKind::Theme => "theme",
         }
+    }
+}
 
-        println!("Please paste the API Token you created on https://plugins.lapce.dev/");
-        let mut token = String::new();
-        stdin().read_line(&mut token).unwrap();
+#[derive(Clone, Copy, ValueEnum)]
+enum SearchSort {
+    Downloads,
+    Created,
+    Updated,
+}
+
+impl SearchSort {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SearchSort::Downloads => "downloads",
+            SearchSort::Created => "created",
+            SearchSort::Updated => "updated",
+        }
+    }
+}
+
+pub fn cli() {
+    let cli = Cli::parse();
 
-        token = token.trim().to_string();
-        if token.is_empty() {
-            eprintln!("Token cannot be empty");
-            std::process::exit(1);
+    match &cli.command {
+        Commands::Login {} => report(cli.format, commands::login(&cli)),
+        Commands::Logout {} => report(cli.format, commands::logout(&cli)),
+        Commands::Publish { trusted, notes } => report(
+            cli.format,
+            commands::publish(&cli, *trusted, notes.as_deref()),
+        ),
+        Commands::Package { output } => report(cli.format, commands::package(output.as_deref())),
+        Commands::Dev { plugins_dir } => {
+            if let Err(e) = dev::dev(cli.format, plugins_dir.as_deref()) {
+                fail(cli.format, e)
+            }
+        }
+        Commands::New { name, kind, author } => {
+            report(cli.format, commands::new(name, *kind, author.as_deref()))
+        }
+        Commands::Version { bump, pre, git_tag } => report(
+            cli.format,
+            commands::version(&cli, bump.as_deref(), pre.as_deref(), *git_tag),
+        ),
+        Commands::Yank {
+            name,
+            version,
+            reason,
+        } => report(
+            cli.format,
+            commands::yank(&cli, name, version, reason.as_deref()),
+        ),
+        Commands::Unyank { name, version } => {
+            report(cli.format, commands::unyank(&cli, name, version))
+        }
+        Commands::Delete { plugin, yes } => {
+            report(cli.format, commands::delete(&cli, plugin, *yes))
         }
+        Commands::Token { command } => match command {
+            TokenCommands::List {} => report(cli.format, commands::token_list(&cli)),
+            TokenCommands::Create { name, scopes } => {
+                report(cli.format, commands::token_create(&cli, name, scopes))
+            }
+            TokenCommands::Revoke { id } => report(cli.format, commands::token_revoke(&cli, *id)),
+        },
+        Commands::TrustedPublisher { command } => match command {
+            TrustedPublisherCommands::List {} => {
+                report(cli.format, commands::trusted_publisher_list(&cli))
+            }
+            TrustedPublisherCommands::Add {
+                plugin,
+                repository,
+                workflow,
+                environment,
+            } => report(
+                cli.format,
+                commands::trusted_publisher_add(
+                    &cli,
+                    plugin,
+                    repository,
+                    workflow,
+                    environment.as_deref(),
+                ),
+            ),
+            TrustedPublisherCommands::Remove { id } => {
+                report(cli.format, commands::trusted_publisher_remove(&cli, *id))
+            }
+        },
+        Commands::Search {
+            query,
+            kind,
+            sort,
+            limit,
+        } => report(
+            cli.format,
+            commands::search(&cli, query.as_deref(), *kind, *sort, *limit),
+        ),
+        Commands::Info { plugin } => report(cli.format, commands::info(&cli, plugin)),
+        Commands::Install {
+            plugin,
+            plugins_dir,
+        } => report(
+            cli.format,
+            commands::install(&cli, plugin, plugins_dir.as_deref()),
+        ),
+        Commands::Uninstall { plugin } => report(cli.format, commands::uninstall(plugin)),
+        Commands::Update { plugins } => report(cli.format, commands::update(plugins)),
+    }
+}
 
-        if let Err(why) = api_credential.set_password(&token) {
-            eprintln!("Failed to save token in system credential store: {why}");
-        };
+/// The token given with `--token` or in the registry's own environment
+/// variable, so a token meant for one registry is never sent to another.
+fn given_token(cli: &Cli, registry: &Registry) -> Option<String> {
+    cli.token.clone().or_else(|| {
+        registry
+            .token_env()
+            .and_then(|var| std::env::var(var).ok())
+            .filter(|token| !token.trim().is_empty())
+    })
+}
 
-        token
+fn auth_token(cli: &Cli, registry: &Registry) -> Result<String, CliError> {
+    if let Some(token) = given_token(cli, registry) {
+        return Ok(token);
     }
+
+    credentials::load_token(registry).ok_or_else(|| {
+        CliError::Auth(match registry.token_env() {
+            Some(var) => format!(
+                "No API token found, run `volts login` or set the {var} environment variable"
+            ),
+            None => "No API token found, run `volts login` or pass --token".to_string(),
+        })
+    })
 }
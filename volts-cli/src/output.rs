@@ -0,0 +1,182 @@
+use std::fmt::{self, Display};
+
+use clap::ValueEnum;
+use reqwest::{blocking::Response, StatusCode};
+use serde::Serialize;
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Format {
+    Text,
+    Json,
+}
+
+/// Failure of a command, each class exits with its own code.
+#[derive(Debug)]
+pub(crate) enum CliError {
+    /// Local file system failure
+    Io(String),
+    /// The plugin or the arguments given are invalid
+    Validation(String),
+    /// No token available, or the registry rejected it
+    Auth(String),
+    /// The registry couldn't be reached
+    Network(String),
+    /// The registry returned an error
+    Server { status: u16, message: String },
+}
+
+impl CliError {
+    fn kind(&self) -> &'static str {
+        match self {
+            CliError::Io(_) => "io",
+            CliError::Validation(_) => "validation",
+            CliError::Auth(_) => "auth",
+            CliError::Network(_) => "network",
+            CliError::Server { .. } => "server",
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CliError::Io(_) => 1,
+            CliError::Validation(_) => 2,
+            CliError::Auth(_) => 3,
+            CliError::Network(_) => 4,
+            CliError::Server { .. } => 5,
+        }
+    }
+
+    fn message(&self) -> &str {
+        match self {
+            CliError::Io(message)
+            | CliError::Validation(message)
+            | CliError::Auth(message)
+            | CliError::Network(message)
+            | CliError::Server { message, .. } => message,
+        }
+    }
+
+    /// Turns an unsuccessful registry response into an error. The registry
+    /// answers with a plain text message for all of its errors.
+    pub fn from_response(resp: Response) -> CliError {
+        let status = resp.status();
+        let message = resp.text().unwrap_or_default();
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => CliError::Auth(message),
+            StatusCode::BAD_REQUEST => CliError::Validation(message),
+            _ => CliError::Server {
+                status: status.as_u16(),
+                message,
+            },
+        }
+    }
+}
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Server { status, message } => {
+                write!(f, "registry error ({status}): {message}")
+            }
+            _ => f.write_str(self.message()),
+        }
+    }
+}
+
+impl From<reqwest::Error> for CliError {
+    fn from(e: reqwest::Error) -> Self {
+        CliError::Network(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for CliError {
+    fn from(e: std::io::Error) -> Self {
+        CliError::Io(e.to_string())
+    }
+}
+
+/// Writes rows as columns aligned to the widest cell.
+pub(crate) fn table(
+    f: &mut fmt::Formatter<'_>,
+    headers: &[&str],
+    rows: &[Vec<String>],
+) -> fmt::Result {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let headers: Vec<String> = headers.iter().map(|h| h.to_string()).collect();
+    let lines: Vec<String> = std::iter::once(&headers)
+        .chain(rows)
+        .map(|row| {
+            let line = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{cell:width$}"))
+                .collect::<Vec<String>>()
+                .join("  ");
+            line.trim_end().to_string()
+        })
+        .collect();
+    f.write_str(&lines.join("\n"))
+}
+
+#[derive(Serialize)]
+struct ErrorOutput<'a> {
+    error: ErrorBody<'a>,
+}
+
+#[derive(Serialize)]
+struct ErrorBody<'a> {
+    kind: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<u16>,
+    message: &'a str,
+}
+
+/// Prints the output of a command in the requested format.
+pub(crate) fn print<T: Serialize + Display>(format: Format, output: &T) {
+    match format {
+        Format::Text => println!("{output}"),
+        Format::Json => println!("{}", serde_json::to_string(output).unwrap()),
+    }
+}
+
+/// Prints an error in the requested format, without exiting.
+pub(crate) fn print_error(format: Format, e: &CliError) {
+    match format {
+        Format::Text => eprintln!("{e}"),
+        Format::Json => {
+            let status = match e {
+                CliError::Server { status, .. } => Some(*status),
+                _ => None,
+            };
+            let output = ErrorOutput {
+                error: ErrorBody {
+                    kind: e.kind(),
+                    status,
+                    message: e.message(),
+                },
+            };
+            println!("{}", serde_json::to_string(&output).unwrap());
+        }
+    }
+}
+
+/// Prints the error and exits with its code.
+pub(crate) fn fail(format: Format, e: CliError) -> ! {
+    print_error(format, &e);
+    std::process::exit(e.exit_code());
+}
+
+/// Prints the result of a command in the requested format, and exits with the
+/// error's code if it failed.
+pub(crate) fn report<T: Serialize + Display>(format: Format, result: Result<T, CliError>) {
+    match result {
+        Ok(output) => print(format, &output),
+        Err(e) => fail(format, e),
+    }
+}
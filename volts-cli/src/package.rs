@@ -0,0 +1,124 @@
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    path::{Path, PathBuf},
+};
+
+use lapce_rpc::plugin::VoltMetadata;
+use tar::Builder;
+use toml_edit::easy as toml;
+use zstd::Encoder;
+
+use crate::{output::CliError, IconTheme};
+
+/// Reads and validates `volt.toml` in the current directory.
+pub(crate) fn read_volt() -> Result<VoltMetadata, CliError> {
+    let volt_path = PathBuf::from("volt.toml");
+    if !volt_path.exists() {
+        return Err(CliError::Validation("volt.toml doesn't exist".to_string()));
+    }
+
+    let s = fs::read_to_string(&volt_path)?;
+    toml::from_str(&s).map_err(|e| CliError::Validation(format!("volt.toml format invalid: {e}")))
+}
+
+/// Files of the plugin in the current directory that make up its archive,
+/// failing if any file `volt.toml` refers to is missing.
+pub(crate) fn plugin_files(volt: &VoltMetadata) -> Result<Vec<PathBuf>, CliError> {
+    let mut files = vec![PathBuf::from("volt.toml")];
+
+    if let Some(wasm) = volt.wasm.as_ref() {
+        let wasm_path = PathBuf::from(wasm);
+        if !wasm_path.exists() {
+            return Err(CliError::Validation(format!("wasm {wasm} not found")));
+        }
+
+        files.push(wasm_path);
+    } else if let Some(themes) = volt.color_themes.as_ref() {
+        if themes.is_empty() {
+            return Err(CliError::Validation("no color theme provided".to_string()));
+        }
+        for theme in themes {
+            let theme_path = PathBuf::from(theme);
+            if !theme_path.exists() {
+                return Err(CliError::Validation(format!(
+                    "color theme {theme} not found"
+                )));
+            }
+
+            files.push(theme_path);
+        }
+    } else if let Some(themes) = volt.icon_themes.as_ref() {
+        if themes.is_empty() {
+            return Err(CliError::Validation("no icon theme provided".to_string()));
+        }
+        for theme in themes {
+            let theme_path = PathBuf::from(theme);
+            if !theme_path.exists() {
+                return Err(CliError::Validation(format!(
+                    "icon theme {theme} not found"
+                )));
+            }
+
+            let s = fs::read_to_string(&theme_path)?;
+            let theme_config: IconTheme = toml::from_str(&s)
+                .map_err(|_| CliError::Validation(format!("icon theme {theme} format invalid")))?;
+
+            let mut icons = HashSet::new();
+            icons.extend(theme_config.icon_theme.ui.values());
+            icons.extend(theme_config.icon_theme.filename.values());
+            icons.extend(theme_config.icon_theme.foldername.values());
+            icons.extend(theme_config.icon_theme.extension.values());
+
+            let cwd = PathBuf::from(".");
+
+            let mut icon_paths = Vec::new();
+            for icon in icons {
+                let icon_path = theme_path.parent().unwrap_or(&cwd).join(icon);
+                if !icon_path.exists() {
+                    return Err(CliError::Validation(format!("icon {icon} not found")));
+                }
+                icon_paths.push(icon_path);
+            }
+
+            files.push(theme_path);
+            files.extend(icon_paths);
+        }
+    } else {
+        return Err(CliError::Validation("not a valid plugin".to_string()));
+    }
+
+    let readme_path = PathBuf::from("README.md");
+    if readme_path.exists() {
+        files.push(readme_path);
+    }
+
+    if let Some(icon) = volt.icon.as_ref() {
+        let icon_path = PathBuf::from(icon);
+        if !icon_path.exists() {
+            return Err(CliError::Validation(
+                "icon not found at the specified path".to_string(),
+            ));
+        }
+        files.push(icon_path);
+    }
+
+    Ok(files)
+}
+
+/// Packs the plugin in the current directory into a zstd compressed tar
+/// archive at `archive_path`, the format the registry accepts for publishing.
+pub(crate) fn package(archive_path: &Path) -> Result<VoltMetadata, CliError> {
+    let volt = read_volt()?;
+    let files = plugin_files(&volt)?;
+
+    let archive = File::create(archive_path)?;
+    let encoder = Encoder::new(archive, 0)?.auto_finish();
+    let mut tar = Builder::new(encoder);
+    for file in &files {
+        tar.append_path(file)?;
+    }
+    tar.finish()?;
+
+    Ok(volt)
+}
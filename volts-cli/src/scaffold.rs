@@ -0,0 +1,265 @@
+use std::{fs, path::Path};
+
+use clap::ValueEnum;
+use lapce_rpc::plugin::VoltMetadata;
+use toml_edit::easy as toml;
+
+use crate::output::CliError;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub(crate) enum NewKind {
+    Wasm,
+    ColorTheme,
+    IconTheme,
+}
+
+impl NewKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NewKind::Wasm => "wasm",
+            NewKind::ColorTheme => "color-theme",
+            NewKind::IconTheme => "icon-theme",
+        }
+    }
+}
+
+/// Plugin names end up in registry URLs and in the directory Lapce installs
+/// the plugin to, so only allow what's safe in both.
+pub(crate) fn validate_name(name: &str) -> Result<(), CliError> {
+    let valid = name
+        .chars()
+        .next()
+        .map(|c| c.is_ascii_lowercase())
+        .unwrap_or(false)
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    if !valid {
+        return Err(CliError::Validation(format!(
+            "invalid plugin name {name}, use lowercase letters, digits, `-` and `_`, starting with a letter"
+        )));
+    }
+    Ok(())
+}
+
+/// `my-plugin` becomes `My Plugin`
+fn display_name(name: &str) -> String {
+    name.split(['-', '_'])
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Files of a new plugin, as paths relative to the plugin directory with
+/// their contents.
+pub(crate) fn plugin_files(
+    name: &str,
+    kind: NewKind,
+    author: &str,
+) -> Result<Vec<(String, String)>, CliError> {
+    let display_name = display_name(name);
+
+    let mut volt = format!(
+        r#"name = "{name}"
+version = "0.1.0"
+author = "{author}"
+display-name = "{display_name}"
+description = "A Lapce {} plugin"
+"#,
+        kind.as_str()
+    );
+    let mut files = Vec::new();
+    match kind {
+        NewKind::Wasm => {
+            volt.push_str(&format!(
+                "wasm = \"target/wasm32-wasi/release/{name}.wasm\"\n"
+            ));
+            files.push((
+                "Cargo.toml".to_string(),
+                WASM_CARGO_TOML.replace("{name}", name),
+            ));
+            files.push((
+                ".cargo/config.toml".to_string(),
+                WASM_CARGO_CONFIG.to_string(),
+            ));
+            files.push(("src/main.rs".to_string(), WASM_MAIN_RS.to_string()));
+            files.push((".gitignore".to_string(), "/target\n".to_string()));
+        }
+        NewKind::ColorTheme => {
+            volt.push_str(&format!("color-themes = [\"themes/{name}.toml\"]\n"));
+            files.push((
+                format!("themes/{name}.toml"),
+                COLOR_THEME.replace("{display_name}", &display_name),
+            ));
+        }
+        NewKind::IconTheme => {
+            volt.push_str("icon-themes = [\"icon-theme.toml\"]\n");
+            files.push((
+                "icon-theme.toml".to_string(),
+                ICON_THEME.replace("{display_name}", &display_name),
+            ));
+            files.push(("icons/file.svg".to_string(), FILE_SVG.to_string()));
+            files.push(("icons/folder.svg".to_string(), FOLDER_SVG.to_string()));
+        }
+    }
+
+    // Catch anything the registry would refuse to parse before it's written.
+    toml::from_str::<VoltMetadata>(&volt)
+        .map_err(|e| CliError::Validation(format!("generated volt.toml invalid: {e}")))?;
+
+    files.insert(0, ("volt.toml".to_string(), volt));
+    files.push((
+        "README.md".to_string(),
+        format!("# {display_name}\n\nA Lapce {} plugin.\n", kind.as_str()),
+    ));
+    Ok(files)
+}
+
+/// Writes the files of a new plugin into `dir`, which must not exist yet.
+pub(crate) fn scaffold(dir: &Path, files: &[(String, String)]) -> Result<(), CliError> {
+    if dir.exists() {
+        return Err(CliError::Validation(format!(
+            "{} already exists",
+            dir.display()
+        )));
+    }
+
+    for (path, content) in files {
+        let path = dir.join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, content)?;
+    }
+    Ok(())
+}
+
+const WASM_CARGO_TOML: &str = r#"[package]
+name = "{name}"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+anyhow = "1.0"
+serde = "1.0"
+serde_json = "1.0"
+lapce-plugin = "0.1.1"
+"#;
+
+const WASM_CARGO_CONFIG: &str = r#"[build]
+target = "wasm32-wasi"
+"#;
+
+const WASM_MAIN_RS: &str = r#"use anyhow::Result;
+use lapce_plugin::{
+    psp_types::{
+        lsp_types::{request::Initialize, InitializeParams, MessageType},
+        Request,
+    },
+    register_plugin, LapcePlugin, PLUGIN_RPC,
+};
+use serde_json::Value;
+
+#[derive(Default)]
+struct State {}
+
+register_plugin!(State);
+
+fn initialize(_params: InitializeParams) -> Result<()> {
+    PLUGIN_RPC.window_log_message(MessageType::INFO, "plugin initialized".to_string());
+    Ok(())
+}
+
+impl LapcePlugin for State {
+    fn handle_request(&mut self, _id: u64, method: String, params: Value) {
+        #[allow(clippy::single_match)]
+        match method.as_str() {
+            Initialize::METHOD => {
+                let params: InitializeParams = serde_json::from_value(params).unwrap();
+                if let Err(e) = initialize(params) {
+                    PLUGIN_RPC.window_show_message(
+                        MessageType::ERROR,
+                        format!("plugin returned with error: {e}"),
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+}
+"#;
+
+const COLOR_THEME: &str = r##"[color-theme]
+name = "{display_name}"
+
+[color-theme.base]
+white = "#ABB2BF"
+black = "#282C34"
+grey = "#3E4451"
+blue = "#61AFEF"
+red = "#E06C75"
+yellow = "#E5C07B"
+orange = "#D19A66"
+green = "#98C379"
+purple = "#C678DD"
+cyan = "#56B6C2"
+magenta = "#C678DD"
+
+[color-theme.syntax]
+"comment" = "$grey"
+"constant" = "$yellow"
+"type" = "$yellow"
+"number" = "$orange"
+"struct" = "$yellow"
+"enum" = "$yellow"
+"function" = "$blue"
+"method" = "$blue"
+"keyword" = "$purple"
+"field" = "$red"
+"variable" = "$red"
+"string" = "$green"
+"string.escape" = "$cyan"
+"attribute" = "$yellow"
+
+[color-theme.ui]
+"lapce.error" = "$red"
+"lapce.warn" = "$yellow"
+"editor.background" = "$black"
+"editor.foreground" = "$white"
+"editor.caret" = "$blue"
+"editor.selection" = "$grey"
+"panel.background" = "#21252B"
+"status.background" = "#21252B"
+"source_control.added" = "$green"
+"source_control.removed" = "$red"
+"source_control.modified" = "$blue"
+"##;
+
+const ICON_THEME: &str = r#"[icon-theme]
+name = "{display_name}"
+
+[icon-theme.ui]
+"file" = "icons/file.svg"
+
+[icon-theme.foldername]
+"src" = "icons/folder.svg"
+
+[icon-theme.filename]
+"Cargo.toml" = "icons/file.svg"
+
+[icon-theme.extension]
+"rs" = "icons/file.svg"
+"#;
+
+const FILE_SVG: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 16 16"><path fill="#ABB2BF" d="M3 1h6l4 4v10H3z"/></svg>
+"##;
+
+const FOLDER_SVG: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 16 16"><path fill="#61AFEF" d="M1 3h5l2 2h7v9H1z"/></svg>
+"##;
@@ -0,0 +1,227 @@
+use std::{
+    fs::File,
+    io::{Cursor, Read, Seek, SeekFrom},
+    path::Path,
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use indicatif::{ProgressBar, ProgressStyle};
+use reqwest::{
+    blocking::{Body, Client, RequestBuilder, Response},
+    header::CONTENT_TYPE,
+    Method, StatusCode,
+};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    api::{PublishPayload, UploadSession},
+    config::Registry,
+    output::CliError,
+};
+
+/// Size of the parts archives are uploaded in, well below the registry's
+/// request body limit.
+const PART_SIZE: u64 = 8 * 1024 * 1024;
+const MAX_RETRIES: u32 = 5;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(600);
+
+pub(crate) fn progress_bar(len: u64, show: bool) -> ProgressBar {
+    if !show {
+        return ProgressBar::hidden();
+    }
+    let pb = ProgressBar::new(len);
+    pb.set_style(
+        ProgressStyle::with_template(
+            "uploading [{bar:40}] {bytes}/{total_bytes} {bytes_per_sec} {eta}",
+        )
+        .unwrap()
+        .progress_chars("=> "),
+    );
+    pb
+}
+
+/// Sends the request `request` builds, retrying with exponential backoff when
+/// the registry can't be reached. Requests that aren't `idempotent` are only
+/// retried when they didn't get to the registry at all.
+fn send(
+    pb: &ProgressBar,
+    idempotent: bool,
+    request: impl Fn() -> Result<RequestBuilder, CliError>,
+) -> Result<Response, CliError> {
+    let mut attempt = 0;
+    loop {
+        let result = request()?.send();
+        let reason = match &result {
+            Ok(resp)
+                if idempotent
+                    && (resp.status().is_server_error()
+                        || resp.status() == StatusCode::TOO_MANY_REQUESTS) =>
+            {
+                Some(format!("registry answered {}", resp.status()))
+            }
+            Ok(_) => None,
+            Err(e) if e.is_connect() => Some(e.to_string()),
+            Err(e) if idempotent && (e.is_timeout() || e.is_request() || e.is_body()) => {
+                Some(e.to_string())
+            }
+            Err(_) => None,
+        };
+
+        match reason {
+            Some(reason) if attempt < MAX_RETRIES => {
+                let delay = Duration::from_secs(1 << attempt);
+                pb.println(format!("{reason}, retrying in {}s", delay.as_secs()));
+                thread::sleep(delay);
+                attempt += 1;
+            }
+            _ => return Ok(result?),
+        }
+    }
+}
+
+/// Uploads the archive through an upload session, sending it in parts that
+/// are resent or resumed when the connection fails, then publishes it.
+/// Registries without upload sessions get the archive in a single request.
+pub(crate) fn upload(
+    registry: &Registry,
+    token: &str,
+    archive_path: &Path,
+    notes: Option<&str>,
+    pb: &ProgressBar,
+) -> Result<(), CliError> {
+    let client = Client::builder().timeout(REQUEST_TIMEOUT).build()?;
+    let len = archive_path.metadata()?.len();
+
+    let resp = send(pb, true, || {
+        Ok(client
+            .request(Method::POST, registry.api_url("/plugins/uploads"))
+            .bearer_auth(token))
+    })?;
+    match resp.status() {
+        StatusCode::OK => {}
+        StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED => {
+            return upload_single(&client, registry, token, archive_path, notes, pb);
+        }
+        _ => return Err(CliError::from_response(resp)),
+    }
+    let session: UploadSession = resp.json()?;
+
+    let mut archive = File::open(archive_path)?;
+    let mut received = session.received;
+    while received < len {
+        let offset = received;
+        let mut part = Vec::new();
+        archive.seek(SeekFrom::Start(offset))?;
+        (&mut archive).take(PART_SIZE).read_to_end(&mut part)?;
+        let part_len = part.len() as u64;
+
+        let resp = send(pb, true, || {
+            pb.set_position(offset);
+            Ok(client
+                .request(
+                    Method::PUT,
+                    registry.api_url(&format!("/plugins/uploads/{}?offset={offset}", session.id)),
+                )
+                .bearer_auth(token)
+                .body(Body::sized(
+                    pb.wrap_read(Cursor::new(part.clone())),
+                    part_len,
+                )))
+        })?;
+        match resp.status() {
+            // A conflict tells where the registry wants the upload to continue.
+            StatusCode::OK | StatusCode::CONFLICT => {
+                received = resp.json::<UploadSession>()?.received;
+            }
+            _ => return Err(CliError::from_response(resp)),
+        }
+        if received <= offset {
+            return Err(CliError::Server {
+                status: StatusCode::OK.as_u16(),
+                message: format!("registry didn't accept the upload from byte {offset}"),
+            });
+        }
+        pb.set_position(received);
+    }
+
+    let resp = send(pb, false, || {
+        Ok(client
+            .request(
+                Method::POST,
+                registry.api_url(&format!("/plugins/uploads/{}/finish", session.id)),
+            )
+            .bearer_auth(token)
+            .json(&PublishPayload {
+                notes: notes.map(|notes| notes.to_string()),
+            }))
+    })?;
+    if resp.status() != StatusCode::OK {
+        return Err(CliError::from_response(resp));
+    }
+    Ok(())
+}
+
+fn upload_single(
+    client: &Client,
+    registry: &Registry,
+    token: &str,
+    archive_path: &Path,
+    notes: Option<&str>,
+    pb: &ProgressBar,
+) -> Result<(), CliError> {
+    let len = archive_path.metadata()?.len();
+    // Published versions can't be replaced, so a publish that may have gone
+    // through isn't sent again.
+    let resp = send(pb, false, || {
+        pb.set_position(0);
+        let archive = pb.wrap_read(File::open(archive_path)?);
+        let request = client
+            .request(Method::PUT, registry.api_url("/plugins/new"))
+            .bearer_auth(token);
+        Ok(match notes {
+            Some(notes) => {
+                let (boundary, head, tail) = multipart_parts(notes);
+                let body_len = head.len() as u64 + len + tail.len() as u64;
+                request
+                    .header(
+                        CONTENT_TYPE,
+                        format!("multipart/form-data; boundary={boundary}"),
+                    )
+                    .body(Body::sized(
+                        Cursor::new(head).chain(archive).chain(Cursor::new(tail)),
+                        body_len,
+                    ))
+            }
+            None => request.body(Body::sized(archive, len)),
+        })
+    })?;
+    if resp.status() != StatusCode::OK {
+        return Err(CliError::from_response(resp));
+    }
+    Ok(())
+}
+
+/// The boundary and the parts of a `multipart/form-data` body around the
+/// archive, which is streamed in between rather than read into memory.
+fn multipart_parts(notes: &str) -> (String, Vec<u8>, Vec<u8>) {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let mut hasher = Sha256::new();
+    hasher.update(notes.as_bytes());
+    hasher.update(nanos.to_le_bytes());
+    let boundary = format!("volts-{:x}", hasher.finalize());
+
+    let head = format!(
+        "--{boundary}\r\n\
+         Content-Disposition: form-data; name=\"notes\"\r\n\r\n\
+         {notes}\r\n\
+         --{boundary}\r\n\
+         Content-Disposition: form-data; name=\"archive\"; filename=\"plugin.volt\"\r\n\
+         Content-Type: application/octet-stream\r\n\r\n"
+    );
+    let tail = format!("\r\n--{boundary}--\r\n");
+    (boundary, head.into_bytes(), tail.into_bytes())
+}
@@ -0,0 +1,217 @@
+use std::{fs, path::Path};
+
+use semver::{Prerelease, Version};
+use toml_edit::{Document, Value};
+
+use crate::output::CliError;
+
+fn parse(version: &str) -> Result<Version, CliError> {
+    Version::parse(version)
+        .map_err(|e| CliError::Validation(format!("invalid version {version}: {e}")))
+}
+
+fn pre_release(tag: &str, n: u64) -> Result<Prerelease, CliError> {
+    Prerelease::new(&format!("{tag}.{n}"))
+        .map_err(|e| CliError::Validation(format!("invalid pre-release tag {tag}: {e}")))
+}
+
+/// Computes the version after `current` for `bump`, which is `major`, `minor`,
+/// `patch` or an explicit version. With `pre`, the result is the first
+/// `<pre>.1` pre-release of that version, or with no `bump` the next one in the
+/// current pre-release series.
+pub(crate) fn next_version(
+    current: &str,
+    bump: Option<&str>,
+    pre: Option<&str>,
+) -> Result<Version, CliError> {
+    let current = parse(current)?;
+    // Bumping a pre-release releases the version it was leading up to.
+    let releasing = !current.pre.is_empty();
+
+    let mut next = match bump {
+        Some("major") if releasing && current.minor == 0 && current.patch == 0 => {
+            Version::new(current.major, 0, 0)
+        }
+        Some("major") => Version::new(current.major + 1, 0, 0),
+        Some("minor") if releasing && current.patch == 0 => {
+            Version::new(current.major, current.minor, 0)
+        }
+        Some("minor") => Version::new(current.major, current.minor + 1, 0),
+        Some("patch") if releasing => Version::new(current.major, current.minor, current.patch),
+        Some("patch") => Version::new(current.major, current.minor, current.patch + 1),
+        Some(explicit) => {
+            if pre.is_some() {
+                return Err(CliError::Validation(
+                    "--pre can't be combined with an explicit version".to_string(),
+                ));
+            }
+            return parse(explicit);
+        }
+        None => {
+            let tag = pre.ok_or_else(|| {
+                CliError::Validation(
+                    "give major, minor, patch, a version or --pre <tag>".to_string(),
+                )
+            })?;
+            let mut next = Version::new(current.major, current.minor, current.patch);
+            if !releasing {
+                next.patch += 1;
+            }
+            let n = match current.pre.as_str().split_once('.') {
+                Some((current_tag, n)) if current_tag == tag => {
+                    n.parse::<u64>().map(|n| n + 1).unwrap_or(1)
+                }
+                _ => 1,
+            };
+            next.pre = pre_release(tag, n)?;
+            return Ok(next);
+        }
+    };
+
+    if let Some(tag) = pre {
+        next.pre = pre_release(tag, 1)?;
+    }
+    Ok(next)
+}
+
+/// The greatest of the published versions, yanked ones included as the
+/// registry won't accept them again either.
+fn latest<'a>(versions: impl Iterator<Item = &'a str>) -> Option<Version> {
+    versions.filter_map(|v| Version::parse(v).ok()).max()
+}
+
+/// Checks that `next` is greater than every version published to the
+/// registry at `registry_url`.
+pub(crate) fn check_unpublished<'a>(
+    next: &Version,
+    published: impl Iterator<Item = &'a str>,
+    registry_url: &str,
+) -> Result<(), CliError> {
+    match latest(published) {
+        Some(latest) if *next <= latest => Err(CliError::Validation(format!(
+            "v{next} isn't greater than v{latest}, the latest version published to {registry_url}"
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Rewrites `version` in the `volt.toml` at `path`, keeping the rest of the
+/// file, comments and formatting included, as it is.
+pub(crate) fn write_version(path: &Path, version: &Version) -> Result<(), CliError> {
+    let s = fs::read_to_string(path)?;
+    let mut doc: Document = s
+        .parse()
+        .map_err(|e| CliError::Validation(format!("volt.toml format invalid: {e}")))?;
+
+    let mut value = Value::from(version.to_string());
+    if let Some(old) = doc["version"].as_value() {
+        *value.decor_mut() = old.decor().clone();
+    }
+    doc["version"] = toml_edit::Item::Value(value);
+
+    fs::write(path, doc.to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn next(current: &str, bump: Option<&str>, pre: Option<&str>) -> String {
+        next_version(current, bump, pre).unwrap().to_string()
+    }
+
+    #[test]
+    fn bumps_released_versions() {
+        assert_eq!(next("1.2.3", Some("major"), None), "2.0.0");
+        assert_eq!(next("1.2.3", Some("minor"), None), "1.3.0");
+        assert_eq!(next("1.2.3", Some("patch"), None), "1.2.4");
+    }
+
+    #[test]
+    fn bumps_into_pre_releases() {
+        assert_eq!(next("1.2.3", Some("major"), Some("beta")), "2.0.0-beta.1");
+        assert_eq!(next("1.2.3", Some("minor"), Some("rc")), "1.3.0-rc.1");
+        assert_eq!(next("1.2.3", Some("patch"), Some("alpha")), "1.2.4-alpha.1");
+    }
+
+    #[test]
+    fn continues_pre_release_series() {
+        assert_eq!(next("1.2.3", None, Some("beta")), "1.2.4-beta.1");
+        assert_eq!(next("1.2.4-beta.1", None, Some("beta")), "1.2.4-beta.2");
+        assert_eq!(next("1.2.4-beta.9", None, Some("beta")), "1.2.4-beta.10");
+        // Another tag starts its own series for the same version
+        assert_eq!(next("1.2.4-beta.3", None, Some("rc")), "1.2.4-rc.1");
+        // A pre-release without a number starts the series over
+        assert_eq!(next("1.2.4-beta", None, Some("beta")), "1.2.4-beta.1");
+    }
+
+    #[test]
+    fn bumping_pre_release_releases_it() {
+        assert_eq!(next("2.0.0-beta.2", Some("major"), None), "2.0.0");
+        assert_eq!(next("1.3.0-rc.1", Some("minor"), None), "1.3.0");
+        assert_eq!(next("1.2.4-alpha.1", Some("patch"), None), "1.2.4");
+        // Unless the pre-release leads up to a smaller bump
+        assert_eq!(next("1.3.1-beta.1", Some("minor"), None), "1.4.0");
+        assert_eq!(next("1.3.0-beta.1", Some("major"), None), "2.0.0");
+    }
+
+    #[test]
+    fn takes_explicit_versions() {
+        assert_eq!(next("1.2.3", Some("3.0.0-rc.1"), None), "3.0.0-rc.1");
+        assert!(matches!(
+            next_version("1.2.3", Some("3.0.0"), Some("beta")),
+            Err(CliError::Validation(_))
+        ));
+        assert!(matches!(
+            next_version("1.2.3", Some("three"), None),
+            Err(CliError::Validation(_))
+        ));
+        assert!(matches!(
+            next_version("1.2.3", None, None),
+            Err(CliError::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_versions_not_above_published() {
+        let published = ["0.1.0", "1.2.0", "not a version", "1.10.0-beta.1"];
+        let check = |next: &str| {
+            check_unpublished(
+                &Version::parse(next).unwrap(),
+                published.iter().copied(),
+                "https://plugins.lapce.dev",
+            )
+        };
+        assert!(check("1.10.0").is_ok());
+        assert!(matches!(
+            check("1.10.0-beta.1"),
+            Err(CliError::Validation(_))
+        ));
+        assert!(matches!(check("1.2.0"), Err(CliError::Validation(_))));
+        assert!(matches!(check("1.1.0"), Err(CliError::Validation(_))));
+        assert!(check_unpublished(
+            &Version::new(0, 0, 1),
+            std::iter::empty(),
+            "https://plugins.lapce.dev"
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn write_version_keeps_formatting() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("volt.toml");
+        fs::write(
+            &path,
+            "# my plugin\nname = \"test\"\nversion   =   \"0.1.0\"  # bumped by volts\nauthor = \"me\"\n",
+        )
+        .unwrap();
+
+        write_version(&path, &Version::parse("0.2.0-beta.1").unwrap()).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# my plugin\nname = \"test\"\nversion   =   \"0.2.0-beta.1\"  # bumped by volts\nauthor = \"me\"\n"
+        );
+    }
+}
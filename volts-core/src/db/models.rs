@@ -1,8 +1,11 @@
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 
-use crate::db::schema::{api_tokens, plugins, users, versions};
+use crate::db::schema::{
+    api_tokens, deleted_plugins, plugins, trusted_publishers, users, versions,
+};
 use crate::util::rfc3339;
+use crate::{TOKEN_SCOPE_PUBLISH, TOKEN_SCOPE_YANK};
 
 #[derive(
     Queryable, Debug, Identifiable, Associations, Serialize, Deserialize, Clone, PartialEq, Eq,
@@ -21,6 +24,24 @@ pub struct ApiToken {
     pub last_used_at: Option<NaiveDateTime>,
     #[serde(skip)]
     pub revoked: bool,
+    /// `None` for tokens created before scopes existed, which can publish and yank
+    #[serde(default)]
+    pub endpoint_scopes: Option<Vec<String>>,
+    /// Set for the short-lived tokens handed out to trusted publishers
+    #[serde(default, with = "rfc3339::option")]
+    pub expires_at: Option<NaiveDateTime>,
+    /// The only plugin the token can publish, if it's limited to one
+    #[serde(skip)]
+    pub plugin_id: Option<i32>,
+}
+
+impl ApiToken {
+    pub fn has_scope(&self, scope: &str) -> bool {
+        match self.endpoint_scopes.as_ref() {
+            Some(scopes) => scopes.iter().any(|s| s == scope),
+            None => scope == TOKEN_SCOPE_PUBLISH || scope == TOKEN_SCOPE_YANK,
+        }
+    }
 }
 
 #[derive(Queryable, Debug, Identifiable)]
@@ -29,6 +50,8 @@ pub struct User {
     pub gh_access_token: String,
     pub gh_login: String,
     pub gh_id: i32,
+    pub gh_avatar: Option<String>,
+    pub name: Option<String>,
 }
 
 #[derive(Queryable, Debug, Identifiable, Associations)]
@@ -44,6 +67,10 @@ pub struct Plugin {
     pub downloads: i32,
     pub repository: Option<String>,
     pub wasm: bool,
+    /// The repository is on GitHub and the publisher could push to it
+    pub repository_verified: bool,
+    pub deprecated: bool,
+    pub deprecation_message: Option<String>,
 }
 
 #[derive(Queryable, Debug, Identifiable, Associations)]
@@ -56,4 +83,45 @@ pub struct Version {
     pub num: String,
     pub yanked: bool,
     pub downloads: i32,
+    pub checksum: Option<String>,
+    pub yank_reason: Option<String>,
+    pub release_notes: Option<String>,
+}
+
+/// A CI workflow allowed to publish a plugin with OIDC identity tokens
+#[derive(
+    Queryable, Debug, Identifiable, Associations, Serialize, Deserialize, Clone, PartialEq, Eq,
+)]
+#[diesel(belongs_to(Plugin))]
+pub struct TrustedPublisher {
+    pub id: i32,
+    #[serde(skip)]
+    pub plugin_id: i32,
+    /// `owner/repo` on GitHub
+    pub repository: String,
+    /// File name of the workflow in `.github/workflows`
+    pub workflow: String,
+    pub environment: Option<String>,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+    /// GitHub's ids of the repository and its owner, which unlike the name
+    /// can't be taken over by someone else. `None` for publishers added before
+    /// they were recorded.
+    #[serde(skip)]
+    pub repository_id: Option<String>,
+    #[serde(skip)]
+    pub repository_owner_id: Option<String>,
+}
+
+/// A deleted plugin, keeping its name reserved until `reserved_until`
+#[derive(Queryable, Debug, Identifiable)]
+pub struct DeletedPlugin {
+    pub id: i32,
+    pub user_id: i32,
+    pub author: String,
+    pub name: String,
+    /// The owner, or the admin who deleted it
+    pub deleted_by: i32,
+    pub deleted_at: NaiveDateTime,
+    pub reserved_until: NaiveDateTime,
 }
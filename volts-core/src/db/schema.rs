@@ -9,6 +9,29 @@ diesel::table! {
         created_at -> Timestamp,
         last_used_at -> Nullable<Timestamp>,
         revoked -> Bool,
+        endpoint_scopes -> Nullable<Array<Text>>,
+        expires_at -> Nullable<Timestamp>,
+        plugin_id -> Nullable<Int4>,
+    }
+}
+
+diesel::table! {
+    deleted_plugins (id) {
+        id -> Int4,
+        user_id -> Int4,
+        author -> Varchar,
+        name -> Varchar,
+        deleted_by -> Int4,
+        deleted_at -> Timestamp,
+        reserved_until -> Timestamp,
+    }
+}
+
+diesel::table! {
+    plugin_owners (plugin_id, user_id) {
+        plugin_id -> Int4,
+        user_id -> Int4,
+        created_at -> Timestamp,
     }
 }
 
@@ -24,6 +47,22 @@ diesel::table! {
         downloads -> Int4,
         repository -> Nullable<Varchar>,
         wasm -> Bool,
+        repository_verified -> Bool,
+        deprecated -> Bool,
+        deprecation_message -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    trusted_publishers (id) {
+        id -> Int4,
+        plugin_id -> Int4,
+        repository -> Varchar,
+        workflow -> Varchar,
+        environment -> Nullable<Varchar>,
+        created_at -> Timestamp,
+        repository_id -> Nullable<Varchar>,
+        repository_owner_id -> Nullable<Varchar>,
     }
 }
 
@@ -33,6 +72,16 @@ diesel::table! {
         gh_access_token -> Varchar,
         gh_login -> Varchar,
         gh_id -> Int4,
+        gh_avatar -> Nullable<Varchar>,
+        name -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    version_downloads (version_id, date) {
+        version_id -> Int4,
+        date -> Date,
+        downloads -> Int4,
     }
 }
 
@@ -45,11 +94,27 @@ diesel::table! {
         num -> Varchar,
         yanked -> Bool,
         downloads -> Int4,
+        checksum -> Nullable<Varchar>,
+        yank_reason -> Nullable<Varchar>,
+        release_notes -> Nullable<Varchar>,
     }
 }
 
 diesel::joinable!(api_tokens -> users (user_id));
+diesel::joinable!(plugin_owners -> plugins (plugin_id));
+diesel::joinable!(plugin_owners -> users (user_id));
 diesel::joinable!(plugins -> users (user_id));
+diesel::joinable!(trusted_publishers -> plugins (plugin_id));
+diesel::joinable!(version_downloads -> versions (version_id));
 diesel::joinable!(versions -> plugins (plugin_id));
 
-diesel::allow_tables_to_appear_in_same_query!(api_tokens, plugins, users, versions,);
+diesel::allow_tables_to_appear_in_same_query!(
+    api_tokens,
+    deleted_plugins,
+    plugin_owners,
+    plugins,
+    trusted_publishers,
+    users,
+    version_downloads,
+    versions,
+);
@@ -5,8 +5,9 @@ pub mod util;
 extern crate diesel;
 
 use chrono::NaiveDateTime;
-use db::models::ApiToken;
+use db::models::{ApiToken, TrustedPublisher};
 use serde::{Deserialize, Serialize};
+use util::rfc3339;
 
 #[derive(Serialize, Deserialize)]
 pub struct MeUser {
@@ -30,9 +31,27 @@ pub struct EncodeApiToken {
     pub plaintext: String,
 }
 
+/// Allows publishing new versions
+pub const TOKEN_SCOPE_PUBLISH: &str = "publish";
+/// Allows yanking and unyanking versions
+pub const TOKEN_SCOPE_YANK: &str = "yank";
+/// Allows listing, creating and revoking API tokens
+pub const TOKEN_SCOPE_TOKENS: &str = "tokens";
+/// Allows deleting plugins
+pub const TOKEN_SCOPE_DELETE: &str = "delete";
+pub const TOKEN_SCOPES: &[&str] = &[
+    TOKEN_SCOPE_PUBLISH,
+    TOKEN_SCOPE_YANK,
+    TOKEN_SCOPE_TOKENS,
+    TOKEN_SCOPE_DELETE,
+];
+
 #[derive(Serialize, Deserialize)]
 pub struct NewTokenPayload {
     pub name: String,
+    /// Scopes of the new token, publish and yank when not given
+    #[serde(default)]
+    pub scopes: Option<Vec<String>>,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Clone)]
@@ -45,10 +64,20 @@ pub struct EncodePlugin {
     pub description: String,
     pub downloads: i32,
     pub repository: Option<String>,
+    /// Whether the publisher had push access to the repository when publishing
+    #[serde(default)]
+    pub repository_verified: bool,
     pub updated_at_ts: i64,
     pub updated_at: String,
     pub released_at: String,
     pub wasm: bool,
+    /// SHA-256 of the version's archive, missing for versions published before checksums were recorded
+    pub checksum: Option<String>,
+    /// The owner no longer maintains the plugin
+    #[serde(default)]
+    pub deprecated: bool,
+    #[serde(default)]
+    pub deprecation_message: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -58,3 +87,209 @@ pub struct PluginList {
     pub offset: usize,
     pub plugins: Vec<EncodePlugin>,
 }
+
+/// Daily downloads of a plugin's versions, one count per entry of `dates`
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Default)]
+pub struct DownloadStats {
+    /// `YYYY-MM-DD`, oldest first
+    pub dates: Vec<String>,
+    /// Versions downloaded in the period, newest first
+    pub versions: Vec<VersionDownloads>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct VersionDownloads {
+    pub num: String,
+    pub downloads: Vec<i32>,
+}
+
+/// Public profile of a plugin author
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct UserProfile {
+    pub login: String,
+    pub name: Option<String>,
+    pub avatar_url: Option<String>,
+    /// Downloads across all of the user's plugins
+    pub downloads: i64,
+    /// Plugins with a version that isn't yanked, most downloaded first
+    pub plugins: Vec<EncodePlugin>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct EncodeVersion {
+    pub num: String,
+    pub yanked: bool,
+    pub downloads: i32,
+    pub released_at: String,
+    pub checksum: Option<String>,
+    /// Why the version was yanked, if the owner said so
+    #[serde(default)]
+    pub yank_reason: Option<String>,
+    /// Given when publishing, or taken from the version's section of
+    /// `CHANGELOG.md`
+    #[serde(default)]
+    pub release_notes: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct VersionList {
+    pub versions: Vec<EncodeVersion>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PluginVersionId {
+    pub author: String,
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UpdateCheckPayload {
+    pub plugins: Vec<PluginVersionId>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PluginUpdate {
+    pub author: String,
+    pub name: String,
+    pub version: String,
+    /// Whether the installed version has been yanked
+    pub yanked: bool,
+    /// Why the installed version was yanked
+    #[serde(default)]
+    pub yank_reason: Option<String>,
+    /// The latest version, if it's newer than the installed one
+    pub latest: Option<EncodeVersion>,
+    /// Release notes of the versions newer than the installed one, newest
+    /// first
+    #[serde(default)]
+    pub release_notes: Vec<ReleaseNotes>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ReleaseNotes {
+    pub num: String,
+    pub notes: String,
+}
+
+/// One of the plugins the signed in user owns, with all its versions including
+/// yanked ones
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct DashboardPlugin {
+    /// Login of the user the plugin is published under
+    pub author: String,
+    pub name: String,
+    pub display_name: String,
+    pub description: String,
+    pub downloads: i32,
+    /// Latest version that isn't yanked
+    pub latest: Option<String>,
+    pub deprecated: bool,
+    pub deprecation_message: Option<String>,
+    pub updated_at: String,
+    /// Newest first
+    pub versions: Vec<EncodeVersion>,
+    /// Logins of the owners added to the plugin, who can manage it like its
+    /// author
+    pub owners: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct DashboardPluginList {
+    pub plugins: Vec<DashboardPlugin>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NewOwnerPayload {
+    /// GitHub login of a user who has signed in to the registry before
+    pub login: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PluginOwnerList {
+    pub owners: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct DeprecatePayload {
+    /// What to use instead, shown on the plugin page
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+/// Body of requests finishing an upload, which older clients send empty
+#[derive(Serialize, Deserialize, Default)]
+pub struct PublishPayload {
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+/// Body of yank requests, which older clients send empty
+#[derive(Serialize, Deserialize, Default)]
+pub struct YankPayload {
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UpdateCheckList {
+    pub plugins: Vec<PluginUpdate>,
+}
+
+/// An archive being uploaded in parts, published once finished.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct UploadSession {
+    pub id: String,
+    /// Bytes received so far, where the next part starts
+    pub received: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct EncodeTrustedPublisher {
+    #[serde(flatten)]
+    pub publisher: TrustedPublisher,
+    /// Name of the plugin the workflow can publish
+    pub plugin: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TrustedPublisherList {
+    pub trusted_publishers: Vec<EncodeTrustedPublisher>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NewTrustedPublisherPayload {
+    pub plugin: String,
+    /// `owner/repo` on GitHub
+    pub repository: String,
+    /// File name of the workflow in `.github/workflows`, e.g. `release.yml`
+    pub workflow: String,
+    /// GitHub environment the workflow job has to run in
+    pub environment: Option<String>,
+}
+
+/// Exchanges a CI identity token for a short-lived publish token.
+#[derive(Serialize, Deserialize)]
+pub struct TrustedTokenPayload {
+    pub jwt: String,
+    /// Plugin to publish, needed when several plugins trust the same workflow
+    pub plugin: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TrustedToken {
+    pub token: String,
+    pub author: String,
+    pub plugin: String,
+    #[serde(with = "rfc3339")]
+    pub expires_at: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PluginDeleted {
+    pub author: String,
+    pub name: String,
+    /// Until when nobody can publish a plugin with the same name
+    #[serde(with = "rfc3339")]
+    pub reserved_until: NaiveDateTime,
+}
@@ -0,0 +1,163 @@
+use gloo_net::http::Request;
+use sycamore::{
+    component,
+    prelude::{view, Keyed},
+    reactive::{create_effect, create_ref, create_signal, use_context, Scope, Signal},
+    view::View,
+    web::Html,
+};
+use volts_core::{DashboardPlugin, DashboardPluginList, EncodeVersion};
+
+use crate::AppContext;
+
+pub(crate) fn get_dashboard_plugins<'a>(cx: Scope<'a>, plugins: &'a Signal<Vec<DashboardPlugin>>) {
+    let req = Request::get("/api/private/plugins").send();
+    sycamore::futures::spawn_local_scoped(cx, async move {
+        let resp = req.await.unwrap();
+        if !resp.ok() {
+            return;
+        }
+        let list: DashboardPluginList = resp.json().await.unwrap();
+        plugins.set(list.plugins);
+    });
+}
+
+fn version_row<G: Html>(cx: Scope, version: EncodeVersion) -> View<G> {
+    let status = match (version.yanked, version.yank_reason.as_ref()) {
+        (true, Some(reason)) => format!("yanked: {reason}"),
+        (true, None) => "yanked".to_string(),
+        (false, _) => "".to_string(),
+    };
+    let version = create_ref(cx, version);
+    view! { cx,
+        tr(class="border-t") {
+            td(class="pr-6 py-1") { "v" (version.num) }
+            td(class="pr-6 py-1 text-gray-500") { (version.released_at) }
+            td(class="pr-6 py-1") { (version.downloads) }
+            td(class="py-1 text-red-800") { (status) }
+        }
+    }
+}
+
+#[component(inline_props)]
+fn DashboardItem<G: Html>(cx: Scope, login: String, plugin: DashboardPlugin) -> View<G> {
+    let login = create_ref(cx, login);
+    let plugin = create_ref(cx, plugin);
+    let latest = plugin
+        .latest
+        .clone()
+        .map(|latest| format!("v{latest}"))
+        .unwrap_or_else(|| "all versions yanked".to_string());
+
+    view! { cx,
+        li(class="p-5 border-b") {
+            div(class="flex justify-between items-center") {
+                div {
+                    div(class="flex items-baseline") {
+                        a(
+                            class="text-lg font-bold text-blue-500 hover:text-blue-700",
+                            href=format!("/plugins/{}/{}", plugin.author, plugin.name),
+                        ) {
+                            (plugin.display_name)
+                        }
+                        p(class="ml-4 px-2 rounded-md border bg-gray-200") {
+                            (latest)
+                        }
+                        (if plugin.deprecated {
+                            view! {cx,
+                                p(class="ml-2 px-2 rounded text-xs bg-yellow-100 text-yellow-800") {
+                                    "deprecated"
+                                }
+                            }
+                        } else {
+                            view! {cx, }
+                        })
+                    }
+                    p(class="text-sm text-gray-500") {
+                        (plugin.downloads) " Downloads ｜ Updated " (plugin.updated_at)
+                        (if plugin.author != *login {
+                            view! {cx, " ｜ by " (plugin.author) }
+                        } else {
+                            view! {cx, }
+                        })
+                    }
+                }
+                a(
+                    class="rounded-md p-2 border shadow",
+                    href=format!("/account/plugins/{}/{}", plugin.author, plugin.name),
+                ) {
+                    "Manage"
+                }
+            }
+            table(class="table-auto mt-3 text-sm") {
+                thead {
+                    tr(class="text-left") {
+                        th(class="pr-6") { "Version" }
+                        th(class="pr-6") { "Released" }
+                        th(class="pr-6") { "Downloads" }
+                        th {}
+                    }
+                }
+                tbody {
+                    (View::new_fragment(
+                        plugin
+                            .versions
+                            .iter()
+                            .cloned()
+                            .map(|version| version_row(cx, version))
+                            .collect(),
+                    ))
+                }
+            }
+        }
+    }
+}
+
+/// The plugins the signed in user owns with the downloads and status of each
+/// version.
+#[component]
+pub fn PluginDashboard<G: Html>(cx: Scope) -> View<G> {
+    let ctx = use_context::<Signal<AppContext>>(cx);
+    let plugins = create_signal(cx, Vec::new());
+
+    create_effect(cx, move || {
+        if ctx.get().login.is_some() {
+            get_dashboard_plugins(cx, plugins);
+        }
+    });
+
+    view! { cx,
+        (if let Some(login) = ctx.get().login.clone() {
+            view! {cx,
+                div(class="container m-auto px-3") {
+                    div(class="mt-5") {
+                        h1 { "My Plugins" }
+                        (if plugins.get().is_empty() {
+                            view! {cx,
+                                p(class="py-1") {
+                                    "You haven't published any plugins yet."
+                                }
+                            }
+                        } else {
+                            view! {cx, }
+                        })
+                    }
+                    ul(class="my-5 border rounded-md") {
+                        Keyed(
+                            iterable=plugins,
+                            view=move |cx, plugin| {
+                                let login = login.clone();
+                                view! {cx,
+                                    DashboardItem(login=login, plugin=plugin)
+                                }
+                            },
+                            key=|plugin| format!("{}/{}", plugin.author, plugin.name),
+                        )
+                    }
+                }
+            }
+        } else {
+            view! {cx, }
+        })
+    }
+}
@@ -0,0 +1,140 @@
+use gloo_net::http::Request;
+use sycamore::{
+    component,
+    prelude::view,
+    reactive::{create_signal, Scope},
+    view::View,
+    web::Html,
+};
+use volts_core::DownloadStats;
+
+/// Versions drawn separately in the chart, the rest are added up as "Other"
+const MAX_SERIES: usize = 5;
+const COLORS: [&str; MAX_SERIES + 1] = [
+    "#3b82f6", "#10b981", "#f59e0b", "#ef4444", "#8b5cf6", "#9ca3af",
+];
+
+#[derive(Clone, PartialEq, Eq)]
+struct Series {
+    label: String,
+    color: &'static str,
+    downloads: Vec<i32>,
+}
+
+/// The most downloaded versions of the period, followed by the others
+/// combined.
+fn chart_series(stats: DownloadStats) -> Vec<Series> {
+    let mut versions = stats.versions;
+    versions.sort_by_key(|v| -v.downloads.iter().map(|d| *d as i64).sum::<i64>());
+    let mut series: Vec<Series> = versions
+        .iter()
+        .take(MAX_SERIES)
+        .zip(COLORS)
+        .map(|(v, color)| Series {
+            label: format!("v{}", v.num),
+            color,
+            downloads: v.downloads.clone(),
+        })
+        .collect();
+    if versions.len() > MAX_SERIES {
+        let mut downloads = vec![0; stats.dates.len()];
+        for v in &versions[MAX_SERIES..] {
+            for (total, count) in downloads.iter_mut().zip(&v.downloads) {
+                *total += count;
+            }
+        }
+        series.push(Series {
+            label: "Other".to_string(),
+            color: COLORS[MAX_SERIES],
+            downloads,
+        });
+    }
+    series
+}
+
+fn chart_bars<G: Html>(cx: Scope, dates: &[String], series: &[Series]) -> View<G> {
+    let totals: Vec<i32> = (0..dates.len())
+        .map(|day| series.iter().map(|s| s.downloads[day]).sum())
+        .collect();
+    let max = totals.iter().copied().max().unwrap_or(0).max(1);
+
+    View::new_fragment(
+        dates
+            .iter()
+            .zip(totals)
+            .enumerate()
+            .map(|(day, (date, total))| {
+                let title = format!("{date}: {total} downloads");
+                let segments = View::new_fragment(
+                    series
+                        .iter()
+                        .map(|s| {
+                            let style = format!(
+                                "height: {}%; background-color: {}",
+                                s.downloads[day] as f64 * 100.0 / max as f64,
+                                s.color,
+                            );
+                            view! {cx, div(style=style) }
+                        })
+                        .collect(),
+                );
+                view! {cx,
+                    div(class="flex flex-col-reverse h-full flex-1", title=title) {
+                        (segments)
+                    }
+                }
+            })
+            .collect(),
+    )
+}
+
+/// Stacked daily downloads per version, like the chart on crates.io.
+#[component(inline_props)]
+pub fn DownloadChart<G: Html>(cx: Scope, author: String, name: String) -> View<G> {
+    let chart = create_signal(cx, None);
+
+    let req = Request::get(&format!("/api/v1/plugins/{author}/{name}/downloads")).send();
+    sycamore::futures::spawn_local_scoped(cx, async move {
+        let resp = req.await.unwrap();
+        if !resp.ok() {
+            return;
+        }
+        let stats: DownloadStats = resp.json().await.unwrap();
+        let dates = stats.dates.clone();
+        chart.set(Some((dates, chart_series(stats))));
+    });
+
+    view! {cx,
+        (if let Some((dates, series)) = (*chart.get()).clone() {
+            let bars = chart_bars(cx, &dates, &series);
+            let legend = View::new_fragment(
+                series
+                    .iter()
+                    .map(|s| {
+                        let style = format!("background-color: {}", s.color);
+                        let label = s.label.clone();
+                        view! {cx,
+                            div(class="flex items-center mr-3") {
+                                span(class="inline-block w-3 h-3 mr-1 rounded-sm", style=style) {}
+                                (label)
+                            }
+                        }
+                    })
+                    .collect(),
+            );
+            view! {cx,
+                p(class="font-bold mt-8") {
+                    "Downloads in the last " (dates.len()) " days"
+                }
+                div(class="flex items-end h-32 mt-2 gap-px border-b") {
+                    (bars)
+                }
+                div(class="flex flex-wrap mt-2 text-sm text-gray-500") {
+                    (legend)
+                }
+            }
+        } else {
+            view! {cx, }
+        })
+    }
+}
@@ -0,0 +1,430 @@
+use gloo_net::http::Request;
+use sycamore::{
+    component,
+    prelude::{view, Indexed},
+    reactive::{create_effect, create_memo, create_ref, create_signal, use_context, Scope, Signal},
+    view::View,
+    web::Html,
+};
+use volts_core::{
+    DashboardPlugin, DeprecatePayload, EncodeVersion, NewOwnerPayload, PluginOwnerList, YankPayload,
+};
+use wasm_bindgen::JsCast;
+use web_sys::{Event, HtmlInputElement};
+
+use crate::{components::dashboard::get_dashboard_plugins, AppContext};
+
+#[derive(PartialEq, Eq, Clone)]
+struct IndexedVersion {
+    version: EncodeVersion,
+    last: bool,
+}
+
+fn indexed_versions(versions: Vec<EncodeVersion>) -> Vec<IndexedVersion> {
+    let len = versions.len();
+    versions
+        .into_iter()
+        .enumerate()
+        .map(|(i, version)| IndexedVersion {
+            version,
+            last: i + 1 == len,
+        })
+        .collect()
+}
+
+#[component(inline_props)]
+fn VersionItem<'a, G: Html>(
+    cx: Scope<'a>,
+    author: &'a str,
+    name: &'a str,
+    version: IndexedVersion,
+    versions: &'a Signal<Vec<IndexedVersion>>,
+    error: &'a Signal<Option<String>>,
+) -> View<G> {
+    let num = create_ref(cx, version.version.num.clone());
+    let reason = create_signal(cx, String::new());
+    let pending = create_signal(cx, false);
+
+    let set_yanked = move |yanked: bool| {
+        let req = if yanked {
+            Request::put(&format!("/api/private/plugins/{author}/{name}/{num}/yank"))
+                .json(&YankPayload {
+                    reason: Some((*reason.get()).clone()),
+                })
+                .unwrap()
+        } else {
+            Request::put(&format!(
+                "/api/private/plugins/{author}/{name}/{num}/unyank"
+            ))
+        };
+        pending.set(true);
+        sycamore::futures::spawn_local_scoped(cx, async move {
+            let resp = req.send().await.unwrap();
+            if resp.ok() {
+                error.set(None);
+                let yank_reason = Some(reason.get().trim().to_string())
+                    .filter(|reason| yanked && !reason.is_empty());
+                let mut new_versions = (*versions.get()).clone();
+                if let Some(v) = new_versions.iter_mut().find(|v| &v.version.num == num) {
+                    v.version.yanked = yanked;
+                    v.version.yank_reason = yank_reason;
+                }
+                versions.set(new_versions);
+            } else {
+                error.set(Some(resp.text().await.unwrap_or_default()));
+            }
+            pending.set(false);
+        });
+    };
+
+    let handle_input = move |event: Event| {
+        let target: HtmlInputElement = event.target().unwrap().unchecked_into();
+        reason.set(target.value());
+    };
+
+    let yanked = version.version.yanked;
+    let yank_reason = version.version.yank_reason.clone();
+    let released_at = version.version.released_at.clone();
+    let downloads = version.version.downloads;
+
+    view! { cx,
+        li(
+            class=(
+                if version.last {
+                    "p-5"
+                } else {
+                    "p-5 border-b"
+                }
+            ),
+        ) {
+            div(class="flex justify-between items-center") {
+                div {
+                    div(class="flex items-baseline") {
+                        p { "v" (num.clone()) }
+                        (if yanked {
+                            view! {cx,
+                                p(class="ml-2 px-2 rounded text-xs bg-red-100 text-red-800") {
+                                    "yanked"
+                                }
+                            }
+                        } else {
+                            view! {cx, }
+                        })
+                    }
+                    p(class="text-sm text-gray-500") {
+                        "Released " (released_at) " ｜ " (downloads) " Downloads"
+                    }
+                    (if let Some(yank_reason) = yank_reason.clone() {
+                        view! {cx,
+                            p(class="text-sm text-red-800") {
+                                "Reason: " (yank_reason)
+                            }
+                        }
+                    } else {
+                        view! {cx, }
+                    })
+                }
+                (if yanked {
+                    view! {cx,
+                        button(
+                            class="rounded-md p-2 border shadow disabled:bg-gray-200",
+                            disabled=*pending.get(),
+                            on:click=move |_| set_yanked(false),
+                        ) {
+                            "Unyank"
+                        }
+                    }
+                } else {
+                    view! {cx,
+                        div(class="flex") {
+                            input(
+                                class="p-2 border rounded-md w-64",
+                                placeholder="Reason, e.g. broken build",
+                                disabled=*pending.get(),
+                                prop:value=(*reason.get()).clone(),
+                                on:input=handle_input,
+                            ) {}
+                            button(
+                                class="ml-4 rounded-md p-2 border shadow disabled:bg-gray-200",
+                                disabled=*pending.get(),
+                                on:click=move |_| set_yanked(true),
+                            ) {
+                                "Yank"
+                            }
+                        }
+                    }
+                })
+            }
+        }
+    }
+}
+
+#[component(inline_props)]
+fn Deprecation<'a, G: Html>(
+    cx: Scope<'a>,
+    author: &'a str,
+    name: &'a str,
+    plugin: &'a Signal<Option<DashboardPlugin>>,
+    error: &'a Signal<Option<String>>,
+) -> View<G> {
+    let message = create_signal(cx, String::new());
+    let pending = create_signal(cx, false);
+
+    let set_deprecated = move |deprecated: bool| {
+        let req = if deprecated {
+            Request::put(&format!("/api/private/plugins/{author}/{name}/deprecate"))
+                .json(&DeprecatePayload {
+                    message: Some((*message.get()).clone()),
+                })
+                .unwrap()
+        } else {
+            Request::put(&format!("/api/private/plugins/{author}/{name}/undeprecate"))
+        };
+        pending.set(true);
+        sycamore::futures::spawn_local_scoped(cx, async move {
+            let resp = req.send().await.unwrap();
+            if resp.ok() {
+                error.set(None);
+                let deprecation_message = Some(message.get().trim().to_string())
+                    .filter(|message| deprecated && !message.is_empty());
+                if let Some(mut new_plugin) = (*plugin.get()).clone() {
+                    new_plugin.deprecated = deprecated;
+                    new_plugin.deprecation_message = deprecation_message;
+                    plugin.set(Some(new_plugin));
+                }
+            } else {
+                error.set(Some(resp.text().await.unwrap_or_default()));
+            }
+            pending.set(false);
+        });
+    };
+
+    let handle_input = move |event: Event| {
+        let target: HtmlInputElement = event.target().unwrap().unchecked_into();
+        message.set(target.value());
+    };
+
+    view! { cx,
+        div(class="mt-5 p-5 border rounded-md") {
+            p(class="font-bold") { "Deprecation" }
+            (if let Some(current) = (*plugin.get()).clone().filter(|p| p.deprecated) {
+                view! {cx,
+                    div(class="flex justify-between items-center") {
+                        p(class="py-1") {
+                            "This plugin is deprecated. "
+                            (current.deprecation_message.clone().unwrap_or_default())
+                        }
+                        button(
+                            class="rounded-md p-2 border shadow disabled:bg-gray-200",
+                            disabled=*pending.get(),
+                            on:click=move |_| set_deprecated(false),
+                        ) {
+                            "Undeprecate"
+                        }
+                    }
+                }
+            } else {
+                view! {cx,
+                    p(class="py-1") {
+                        "Deprecating marks the plugin as no longer maintained on its page. It stays installable."
+                    }
+                    div(class="flex mt-2") {
+                        input(
+                            class="p-2 border rounded-md w-full",
+                            placeholder="Message, e.g. what to use instead",
+                            disabled=*pending.get(),
+                            prop:value=(*message.get()).clone(),
+                            on:input=handle_input,
+                        ) {}
+                        button(
+                            class="ml-4 rounded-md p-2 border shadow disabled:bg-gray-200",
+                            disabled=*pending.get(),
+                            on:click=move |_| set_deprecated(true),
+                        ) {
+                            "Deprecate"
+                        }
+                    }
+                }
+            })
+        }
+    }
+}
+
+#[component(inline_props)]
+fn Owners<'a, G: Html>(
+    cx: Scope<'a>,
+    author: &'a str,
+    name: &'a str,
+    plugin: &'a Signal<Option<DashboardPlugin>>,
+    error: &'a Signal<Option<String>>,
+) -> View<G> {
+    let login = create_signal(cx, String::new());
+    let pending = create_signal(cx, false);
+    let owners = create_memo(cx, move || {
+        (*plugin.get())
+            .as_ref()
+            .map(|p| p.owners.clone())
+            .unwrap_or_default()
+    });
+
+    let update_owners = move |req: Request| {
+        pending.set(true);
+        sycamore::futures::spawn_local_scoped(cx, async move {
+            let resp = req.send().await.unwrap();
+            if resp.ok() {
+                error.set(None);
+                let list: PluginOwnerList = resp.json().await.unwrap();
+                if let Some(mut new_plugin) = (*plugin.get()).clone() {
+                    new_plugin.owners = list.owners;
+                    plugin.set(Some(new_plugin));
+                }
+                login.set(String::new());
+            } else {
+                error.set(Some(resp.text().await.unwrap_or_default()));
+            }
+            pending.set(false);
+        });
+    };
+
+    let add_owner = move |_: Event| {
+        let req = Request::put(&format!("/api/private/plugins/{author}/{name}/owners"))
+            .json(&NewOwnerPayload {
+                login: (*login.get()).clone(),
+            })
+            .unwrap();
+        update_owners(req);
+    };
+
+    let handle_input = move |event: Event| {
+        let target: HtmlInputElement = event.target().unwrap().unchecked_into();
+        login.set(target.value());
+    };
+
+    view! { cx,
+        div(class="mt-5 p-5 border rounded-md") {
+            p(class="font-bold") { "Owners" }
+            p(class="py-1") {
+                "Owners can yank versions and deprecate the plugin. Only its author can publish it, since plugins are published under their author's name."
+            }
+            ul(class="mt-2") {
+                li(class="py-1 flex justify-between items-center") {
+                    p { (author.to_string()) }
+                    p(class="text-sm text-gray-500") { "author" }
+                }
+                Indexed(
+                    iterable=owners,
+                    view=move |cx, owner| {
+                        let owner = create_ref(cx, owner);
+                        view! {cx,
+                            li(class="py-1 flex justify-between items-center border-t") {
+                                p { (owner.clone()) }
+                                button(
+                                    class="rounded-md p-2 border shadow disabled:bg-gray-200",
+                                    disabled=*pending.get(),
+                                    on:click=move |_| {
+                                        update_owners(Request::delete(&format!(
+                                            "/api/private/plugins/{author}/{name}/owners/{owner}"
+                                        )));
+                                    },
+                                ) {
+                                    "Remove"
+                                }
+                            }
+                        }
+                    },
+                )
+            }
+            div(class="flex mt-2") {
+                input(
+                    class="p-2 border rounded-md w-full",
+                    placeholder="GitHub login of someone who has signed in here before",
+                    disabled=*pending.get(),
+                    prop:value=(*login.get()).clone(),
+                    on:input=handle_input,
+                ) {}
+                button(
+                    class="ml-4 rounded-md p-2 border shadow disabled:bg-gray-200",
+                    disabled=*pending.get(),
+                    on:click=add_owner,
+                ) {
+                    "Add"
+                }
+            }
+        }
+    }
+}
+
+/// Lets the signed in owners of a plugin yank and unyank its versions,
+/// deprecate it and add or remove other owners.
+#[component(inline_props)]
+pub fn PluginManage<G: Html>(cx: Scope, author: String, name: String) -> View<G> {
+    let ctx = use_context::<Signal<AppContext>>(cx);
+    let author = create_ref(cx, author);
+    let name = create_ref(cx, name);
+    let plugins = create_signal(cx, Vec::new());
+    let plugin = create_signal(cx, None);
+    let versions = create_signal(cx, Vec::new());
+    let error = create_signal(cx, None);
+
+    create_effect(cx, move || {
+        if ctx.get().login.is_some() {
+            get_dashboard_plugins(cx, plugins);
+        }
+    });
+
+    create_effect(cx, move || {
+        let current = plugins
+            .get()
+            .iter()
+            .find(|p| &p.author == author && &p.name == name)
+            .cloned();
+        if let Some(current) = current {
+            versions.set(indexed_versions(current.versions.clone()));
+            plugin.set(Some(current));
+        }
+    });
+
+    view! { cx,
+        (if ctx.get().login.is_some() {
+            view! {cx,
+                div(class="container m-auto px-3") {
+                    div(class="mt-5") {
+                        h1(class="flex justify-between") {
+                            span { "Manage " (name.clone()) }
+                            a(
+                                class="text-blue-500 hover:text-blue-700",
+                                href=format!("/plugins/{author}/{name}"),
+                            ) {
+                                "View plugin"
+                            }
+                        }
+                        p(class="py-1") {
+                            "Yanked versions stay downloadable for those who depend on them, but aren't offered as updates."
+                        }
+                    }
+                    (if let Some(message) = (*error.get()).clone() {
+                        view! {cx,
+                            p(class="mt-5 p-4 rounded-md bg-red-100 text-red-800") {
+                                (message)
+                            }
+                        }
+                    } else {
+                        view! {cx, }
+                    })
+                    Deprecation(author=author, name=name, plugin=plugin, error=error)
+                    Owners(author=author, name=name, plugin=plugin, error=error)
+                    ul(class="my-5 border rounded-md") {
+                        Indexed(
+                            iterable=versions,
+                            view=move |cx, version| view! {cx,
+                                VersionItem(author=author, name=name, version=version, versions=versions, error=error)
+                            },
+                        )
+                    }
+                }
+            }
+        } else {
+            view! {cx, }
+        })
+    }
+}
@@ -1,3 +1,8 @@
+pub(crate) mod dashboard;
+pub(crate) mod downloads;
+pub(crate) mod manage;
 pub(crate) mod navbar;
 pub(crate) mod plugin;
 pub(crate) mod token;
+pub(crate) mod user;
+pub(crate) mod versions;
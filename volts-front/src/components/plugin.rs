@@ -4,7 +4,7 @@ use gloo_net::http::Request;
 use sycamore::{
     component,
     prelude::{view, Keyed},
-    reactive::{create_effect, create_selector, create_signal, Scope, Signal},
+    reactive::{create_selector, create_signal, use_context, Scope, Signal},
     view::View,
     web::Html,
 };
@@ -12,10 +12,15 @@ use volts_core::{EncodePlugin, PluginList};
 use wasm_bindgen::{prelude::Closure, JsCast, JsValue};
 use web_sys::{Event, KeyboardEvent};
 
+use crate::{
+    components::{downloads::DownloadChart, versions::VersionHistory},
+    AppContext,
+};
+
 #[derive(PartialEq, Eq, Clone)]
-struct IndexedPlugin {
-    plugin: EncodePlugin,
-    last: bool,
+pub(crate) struct IndexedPlugin {
+    pub(crate) plugin: EncodePlugin,
+    pub(crate) last: bool,
 }
 
 fn get_plugins<'a>(
@@ -74,7 +79,6 @@ fn PluginItem<'a, G: Html>(
     let author = create_signal(cx, plugin.plugin.author.clone());
     let name = create_signal(cx, plugin.plugin.name.clone());
     let version = plugin.plugin.version.clone();
-    let updated_at = plugin.plugin.updated_at_ts;
 
     let handle_img_error = move |event: Event| {
         let target: web_sys::HtmlImageElement = event.target().unwrap().unchecked_into();
@@ -91,7 +95,7 @@ fn PluginItem<'a, G: Html>(
                 ) {
                     img(
                         class="m-4 h-16 w-16",
-                        src=format!("/api/v1/plugins/{}/{}/{}/icon?id={}", author.get(), name.get(), version, updated_at),
+                        src=format!("/api/v1/plugins/{}/{}/{}/icon", author.get(), name.get(), version),
                         on:error=handle_img_error,
                     ) {}
                     div(class="flex flex-col justify-between w-[calc(100%-6rem)] pr-4") {
@@ -125,7 +129,10 @@ fn PluginItem<'a, G: Html>(
 }
 
 #[component(inline_props)]
-fn PluginColumn<'a, G: Html>(cx: Scope<'a>, plugins: &'a Signal<Vec<IndexedPlugin>>) -> View<G> {
+pub(crate) fn PluginColumn<'a, G: Html>(
+    cx: Scope<'a>,
+    plugins: &'a Signal<Vec<IndexedPlugin>>,
+) -> View<G> {
     view! {cx,
         ul {
             Keyed(
@@ -288,18 +295,11 @@ pub fn PluginList<G: Html>(cx: Scope) -> View<G> {
     }
 }
 
+/// Shows a README rendered and sanitized by the registry.
 #[component(inline_props)]
-pub fn ReadmeView<'a, G: Html>(cx: Scope<'a>, text: &'a Signal<String>) -> View<G> {
-    let markdown_html = create_signal(cx, "".to_string());
-    create_effect(cx, || {
-        let text = (*text.get()).to_string();
-        let parser = pulldown_cmark::Parser::new_ext(&text, pulldown_cmark::Options::all());
-        let mut html = "".to_string();
-        pulldown_cmark::html::push_html(&mut html, parser);
-        markdown_html.set(html);
-    });
+pub fn ReadmeView<'a, G: Html>(cx: Scope<'a>, html: &'a Signal<String>) -> View<G> {
     view! { cx,
-        (if text.get().is_empty() {
+        (if html.get().is_empty() {
             view! {cx,
                 p {"No Readme"}
             }
@@ -307,7 +307,7 @@ pub fn ReadmeView<'a, G: Html>(cx: Scope<'a>, text: &'a Signal<String>) -> View<
             view! {cx,
                 div(
                     class="prose prose-neutral",
-                    dangerously_set_inner_html=&markdown_html.get(),
+                    dangerously_set_inner_html=&html.get(),
                 )
             }
         })
@@ -316,6 +316,7 @@ pub fn ReadmeView<'a, G: Html>(cx: Scope<'a>, text: &'a Signal<String>) -> View<
 
 #[component(inline_props)]
 pub fn PluginView<G: Html>(cx: Scope, author: String, name: String) -> View<G> {
+    let ctx = use_context::<Signal<AppContext>>(cx);
     let plugin = create_signal(cx, None);
     let readme = create_signal(cx, "".to_string());
 
@@ -326,7 +327,7 @@ pub fn PluginView<G: Html>(cx: Scope, author: String, name: String) -> View<G> {
         plugin.set(Some(resp.clone()));
 
         let req = Request::get(&format!(
-            "/api/v1/plugins/{author}/{name}/{}/readme",
+            "/api/v1/plugins/{author}/{name}/{}/readme.html",
             resp.version
         ))
         .send();
@@ -355,11 +356,10 @@ pub fn PluginView<G: Html>(cx: Scope, author: String, name: String) -> View<G> {
                     div(class="flex") {
                         img(
                             class="m-8 mt-2 h-24 w-24",
-                            src=format!("/api/v1/plugins/{}/{}/{}/icon?id={}",
+                            src=format!("/api/v1/plugins/{}/{}/{}/icon",
                                 (*plugin.get()).as_ref().unwrap().author,
                                 (*plugin.get()).as_ref().unwrap().name,
-                                (*plugin.get()).as_ref().unwrap().version,
-                                (*plugin.get()).as_ref().unwrap().updated_at_ts),
+                                (*plugin.get()).as_ref().unwrap().version),
                             on:error=handle_img_error,
                         ) {}
                         div(
@@ -377,7 +377,10 @@ pub fn PluginView<G: Html>(cx: Scope, author: String, name: String) -> View<G> {
                                 ((*plugin.get()).as_ref().unwrap().description)
                             }
                             div(class="flex mt-4 flex-wrap") {
-                                p {
+                                a(
+                                    class="text-blue-500 hover:text-blue-700",
+                                    href=format!("/users/{}", (*plugin.get()).as_ref().unwrap().author),
+                                ) {
                                     ((*plugin.get()).as_ref().unwrap().author)
                                 }
                                 p(class="ml-4") {
@@ -389,12 +392,44 @@ pub fn PluginView<G: Html>(cx: Scope, author: String, name: String) -> View<G> {
                             }
                         }
                     }
+                    (if (*plugin.get()).as_ref().unwrap().deprecated {
+                        view! {cx,
+                            div(class="mx-8 mt-4 p-4 rounded-md bg-yellow-100 text-yellow-800") {
+                                p(class="font-bold") { "This plugin is deprecated" }
+                                p {
+                                    ((*plugin.get()).as_ref().unwrap().deprecation_message.clone().unwrap_or_default())
+                                }
+                            }
+                        }
+                    } else {
+                        view! {cx, }
+                    })
                     hr(class="my-8 h-px bg-gray-200 border-0") {}
                     div(class="flex flex-wrap") {
                         div(class="w-full lg:w-2/3 px-10") {
-                            ReadmeView(text=readme)
+                            ReadmeView(html=readme)
+                            VersionHistory(
+                                author=(*plugin.get()).as_ref().unwrap().author.clone(),
+                                name=(*plugin.get()).as_ref().unwrap().name.clone(),
+                            )
                         }
                         div(class="w-full lg:w-1/3 mt-8 lg:mt-0 px-10 lg:px-4") {
+                            (if ctx.get().login.as_ref() == Some(&(*plugin.get()).as_ref().unwrap().author) {
+                                view! {cx,
+                                    a(
+                                        class="block mb-8 p-2 text-center text-blue-50 bg-blue-500 rounded-md shadow",
+                                        href=format!(
+                                            "/account/plugins/{}/{}",
+                                            (*plugin.get()).as_ref().unwrap().author,
+                                            (*plugin.get()).as_ref().unwrap().name,
+                                        ),
+                                    ) {
+                                        "Manage plugin"
+                                    }
+                                }
+                            } else {
+                                view! {cx, }
+                            })
                             p(class="font-bold") {
                                 "Repository"
                             }
@@ -406,10 +441,30 @@ pub fn PluginView<G: Html>(cx: Scope, author: String, name: String) -> View<G> {
                                         a(
                                             class="text-blue-500 hover:text-blue-700",
                                             target="_blank",
+                                            rel="nofollow noopener",
                                             href=(*plugin.get()).as_ref().unwrap().repository.clone().unwrap(),
                                         ) {
                                             ((*plugin.get()).as_ref().unwrap().repository.clone().unwrap())
                                         }
+                                        (if (*plugin.get()).as_ref().unwrap().repository_verified {
+                                            view!{cx,
+                                                p(
+                                                    class="mt-1 inline-block rounded px-2 py-0.5 text-xs bg-green-100 text-green-800",
+                                                    title="The publisher has push access to this repository",
+                                                ) {
+                                                    "verified"
+                                                }
+                                            }
+                                        } else {
+                                            view!{cx,
+                                                p(
+                                                    class="mt-1 inline-block rounded px-2 py-0.5 text-xs bg-gray-100 text-gray-600",
+                                                    title="The publisher's access to this repository couldn't be checked",
+                                                ) {
+                                                    "unverified"
+                                                }
+                                            }
+                                        })
                                     }
                                 })
                             }
@@ -433,7 +488,12 @@ pub fn PluginView<G: Html>(cx: Scope, author: String, name: String) -> View<G> {
                                                 "Author"
                                             }
                                             td {
-                                                ((*plugin.get()).as_ref().unwrap().author)
+                                                a(
+                                                    class="text-blue-500 hover:text-blue-700",
+                                                    href=format!("/users/{}", (*plugin.get()).as_ref().unwrap().author),
+                                                ) {
+                                                    ((*plugin.get()).as_ref().unwrap().author)
+                                                }
                                             }
                                         }
                                         tr {
@@ -455,6 +515,10 @@ pub fn PluginView<G: Html>(cx: Scope, author: String, name: String) -> View<G> {
                                     }
                                 }
                             }
+                            DownloadChart(
+                                author=(*plugin.get()).as_ref().unwrap().author.clone(),
+                                name=(*plugin.get()).as_ref().unwrap().name.clone(),
+                            )
                         }
                     }
                 }
@@ -6,7 +6,10 @@ use sycamore::{
     view::View,
     web::Html,
 };
-use volts_core::{db::models::ApiToken, ApiTokenList, EncodeApiToken, NewTokenPayload};
+use volts_core::{
+    db::models::ApiToken, ApiTokenList, EncodeApiToken, NewTokenPayload, TOKEN_SCOPES,
+    TOKEN_SCOPE_PUBLISH, TOKEN_SCOPE_YANK,
+};
 use wasm_bindgen::JsCast;
 use web_sys::{Event, HtmlInputElement};
 
@@ -43,8 +46,23 @@ fn TokenItem<'a, G: Html>(
             ),
         ) {
             div(class="flex justify-between items-center") {
-                p {
-                    (token.token.name)
+                div {
+                    p {
+                        (token.token.name)
+                    }
+                    p(class="text-sm text-gray-500") {
+                        (token
+                            .token
+                            .endpoint_scopes
+                            .clone()
+                            .unwrap_or_else(|| {
+                                vec![
+                                    TOKEN_SCOPE_PUBLISH.to_string(),
+                                    TOKEN_SCOPE_YANK.to_string(),
+                                ]
+                            })
+                            .join(", "))
+                    }
                 }
                 (if *create_selector(cx, || *revoking.get()).get() {
                     view! {cx,
@@ -112,8 +130,13 @@ pub fn TokenList<G: Html>(cx: Scope) -> View<G> {
     let creating = create_signal(cx, false);
 
     let new_token_name = create_signal(cx, None);
+    let new_token_scopes = create_signal(cx, Vec::new());
     let handle_new_token = move |_| {
         new_token_name.set(Some("".to_string()));
+        new_token_scopes.set(vec![
+            TOKEN_SCOPE_PUBLISH.to_string(),
+            TOKEN_SCOPE_YANK.to_string(),
+        ]);
         creating.set(false);
     };
 
@@ -143,6 +166,7 @@ pub fn TokenList<G: Html>(cx: Scope) -> View<G> {
             let req = Request::post("/api/v1/me/tokens")
                 .json(&NewTokenPayload {
                     name: name.to_string(),
+                    scopes: Some((*new_token_scopes.get()).clone()),
                 })
                 .unwrap();
             sycamore::futures::spawn_local_scoped(cx, async move {
@@ -219,6 +243,42 @@ pub fn TokenList<G: Html>(cx: Scope) -> View<G> {
                                     })
                                 }
                             }
+                            div(class="flex mt-2") {
+                                (View::new_fragment(
+                                    TOKEN_SCOPES
+                                        .iter()
+                                        .map(|scope| {
+                                            let scope = scope.to_string();
+                                            let checked = new_token_scopes.get().contains(&scope);
+                                            let handle_toggle = {
+                                                let scope = scope.clone();
+                                                move |_| {
+                                                    let mut scopes = (*new_token_scopes.get()).clone();
+                                                    match scopes.iter().position(|s| s == &scope) {
+                                                        Some(i) => {
+                                                            scopes.remove(i);
+                                                        }
+                                                        None => scopes.push(scope.clone()),
+                                                    }
+                                                    new_token_scopes.set(scopes);
+                                                }
+                                            };
+                                            view! { cx,
+                                                label(class="mr-4") {
+                                                    input(
+                                                        class="mr-1",
+                                                        type="checkbox",
+                                                        prop:checked=checked,
+                                                        disabled=*creating.get(),
+                                                        on:change=handle_toggle,
+                                                    ) {}
+                                                    (scope)
+                                                }
+                                            }
+                                        })
+                                        .collect(),
+                                ))
+                            }
                         }
                     } else {
                         view! { cx,
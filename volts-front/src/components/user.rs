@@ -0,0 +1,83 @@
+use gloo_net::http::Request;
+use sycamore::{
+    component,
+    prelude::view,
+    reactive::{create_ref, create_signal, Scope, Signal},
+    view::View,
+    web::Html,
+};
+use volts_core::UserProfile;
+
+use crate::components::plugin::{IndexedPlugin, PluginColumn};
+
+fn get_profile<'a>(
+    cx: Scope<'a>,
+    login: &str,
+    profile: &'a Signal<Option<UserProfile>>,
+    plugins: &'a Signal<Vec<IndexedPlugin>>,
+) {
+    let req = Request::get(&format!("/api/v1/users/{login}")).send();
+    sycamore::futures::spawn_local_scoped(cx, async move {
+        let resp = req.await.unwrap();
+        if !resp.ok() {
+            return;
+        }
+        let user: UserProfile = resp.json().await.unwrap();
+        let len = user.plugins.len();
+        plugins.set(
+            user.plugins
+                .iter()
+                .cloned()
+                .enumerate()
+                .map(|(i, plugin)| IndexedPlugin {
+                    plugin,
+                    last: i + 1 == len,
+                })
+                .collect(),
+        );
+        profile.set(Some(user));
+    });
+}
+
+#[component(inline_props)]
+pub fn UserView<G: Html>(cx: Scope, login: String) -> View<G> {
+    let profile = create_signal(cx, None);
+    let plugins = create_signal(cx, Vec::new());
+    get_profile(cx, &login, profile, plugins);
+
+    view! {cx,
+        (if let Some(user) = (*profile.get()).clone() {
+            let user = create_ref(cx, user);
+            view! {cx,
+                div(class="container m-auto mt-10 px-3") {
+                    div(class="flex items-center") {
+                        img(
+                            class="m-8 mt-2 h-24 w-24 rounded-full",
+                            src=user.avatar_url.clone().unwrap_or_else(|| "/static/volt.png".to_string()),
+                        ) {}
+                        div {
+                            p(class="text-4xl font-bold") {
+                                (user.name.clone().unwrap_or_else(|| user.login.clone()))
+                            }
+                            a(
+                                class="text-lg text-blue-500 hover:text-blue-700",
+                                target="_blank",
+                                rel="nofollow noopener",
+                                href=format!("https://github.com/{}", user.login),
+                            ) {
+                                (user.login.clone())
+                            }
+                            p(class="mt-1 text-gray-500") {
+                                (user.plugins.len()) " Plugins ｜ " (user.downloads) " Downloads"
+                            }
+                        }
+                    }
+                    hr(class="my-8 h-px bg-gray-200 border-0") {}
+                    PluginColumn(plugins=plugins)
+                }
+            }
+        } else {
+            view! {cx, }
+        })
+    }
+}
@@ -0,0 +1,76 @@
+use gloo_net::http::Request;
+use sycamore::{
+    component,
+    prelude::view,
+    reactive::{create_signal, Scope},
+    view::View,
+    web::Html,
+};
+use volts_core::{EncodeVersion, VersionList};
+
+fn version_item<G: Html>(cx: Scope, version: EncodeVersion) -> View<G> {
+    let num = format!("v{}", version.num);
+    let released_at = version.released_at.clone();
+    let status = match (version.yanked, version.yank_reason.as_ref()) {
+        (true, Some(reason)) => format!("yanked: {reason}"),
+        (true, None) => "yanked".to_string(),
+        (false, _) => "".to_string(),
+    };
+    let header = view! {cx,
+        span(class="font-bold") { (num) }
+        span(class="ml-4 text-gray-500") { (released_at) }
+        span(class="ml-4 text-red-800") { (status) }
+    };
+
+    match version.release_notes {
+        // Notes are Markdown, shown as written rather than rendered
+        Some(notes) => view! {cx,
+            li(class="py-2 border-t") {
+                details {
+                    summary(class="cursor-pointer") { (header) }
+                    p(class="mt-2 text-sm whitespace-pre-line") { (notes) }
+                }
+            }
+        },
+        None => view! {cx,
+            li(class="py-2 border-t") { (header) }
+        },
+    }
+}
+
+/// Every version of the plugin, newest first, with its release notes.
+#[component(inline_props)]
+pub fn VersionHistory<G: Html>(cx: Scope, author: String, name: String) -> View<G> {
+    let versions = create_signal(cx, Vec::new());
+
+    let req = Request::get(&format!("/api/v1/plugins/{author}/{name}/versions")).send();
+    sycamore::futures::spawn_local_scoped(cx, async move {
+        let resp = req.await.unwrap();
+        if !resp.ok() {
+            return;
+        }
+        let list: VersionList = resp.json().await.unwrap();
+        versions.set(list.versions);
+    });
+
+    view! {cx,
+        (if versions.get().is_empty() {
+            view! {cx, }
+        } else {
+            let items = View::new_fragment(
+                versions
+                    .get()
+                    .iter()
+                    .cloned()
+                    .map(|version| version_item(cx, version))
+                    .collect(),
+            );
+            view! {cx,
+                p(class="font-bold mt-8") { "Versions" }
+                ul(class="mt-2") {
+                    (items)
+                }
+            }
+        })
+    }
+}
@@ -1,9 +1,12 @@
 pub(crate) mod components;
 
 use components::{
+    dashboard::PluginDashboard,
+    manage::PluginManage,
     navbar::Navbar,
     plugin::{PluginList, PluginSearch, PluginSearchIndex, PluginView},
     token::TokenList,
+    user::UserView,
 };
 use gloo_net::http::Request;
 use sycamore::{
@@ -23,12 +26,16 @@ enum AppRoutes {
     Index,
     #[to("/account")]
     Account,
+    #[to("/account/plugins/<author>/<name>")]
+    ManagePlugin { author: String, name: String },
     #[to("/plugins/<author>/<name>")]
     Plugin { author: String, name: String },
     #[to("/search/<query>")]
     Search { query: String },
     #[to("/search")]
     SearchIndex,
+    #[to("/users/<login>")]
+    User { login: String },
     #[not_found]
     NotFound,
 }
@@ -48,6 +55,7 @@ fn Index<G: Html>(cx: Scope) -> View<G> {
 #[component]
 fn Account<G: Html>(cx: Scope) -> View<G> {
     view! { cx,
+        PluginDashboard {}
         TokenList {}
     }
 }
@@ -84,6 +92,9 @@ pub fn start_front() {
                                 AppRoutes::Account => view! {cx,
                                     Account
                                 },
+                                AppRoutes::ManagePlugin { author, name } => view! {cx,
+                                    PluginManage(author=author.clone(), name=name.clone())
+                                },
                                 AppRoutes::Plugin { author, name } => view! {cx,
                                     PluginView(author=author.clone(), name=name.clone())
                                 },
@@ -93,6 +104,9 @@ pub fn start_front() {
                                 AppRoutes::SearchIndex => view! { cx,
                                     PluginSearchIndex
                                 },
+                                AppRoutes::User { login } => view! {cx,
+                                    UserView(login=login.clone())
+                                },
                                 AppRoutes::NotFound => view! {cx,
                                     p(class="text-lg") {
                                         "404 Not Found"